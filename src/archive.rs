@@ -0,0 +1,240 @@
+// a whole directory of resources in one file:
+//
+//   magic `CRPK`, version: u32, file count: u32
+//   for each file: path length: u32, path (utf-8, `/` separated),
+//                  offset: u64, packed length: u64, unpacked length: u64
+//   the zlib compressed files, offsets counting from the start of the archive
+//
+// numbers are little endian
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use miniz_oxide::{deflate::compress_to_vec_zlib, inflate::decompress_to_vec_zlib};
+
+use crate::error::Error;
+
+const MAGIC: &[u8; 4] = b"CRPK";
+const VERSION: u32 = 1;
+const COMPRESSION_LEVEL: u8 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Entry {
+    offset: usize,
+    packed: usize,
+    size: usize,
+}
+
+#[derive(Debug)]
+pub struct Archive {
+    path: PathBuf,
+    entries: HashMap<String, Entry>,
+    data: Vec<u8>,
+}
+impl Archive {
+    pub fn open<T: AsRef<Path>>(path: T) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|e| Error::Io(path.to_owned(), e))?;
+        Self::parse(path, data)
+    }
+    fn parse(path: &Path, data: Vec<u8>) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::InvalidArchive(path.to_owned(), reason.to_owned());
+        let mut r = Reader {
+            data: &data,
+            pos: 0,
+        };
+        if r.bytes(4) != Some(MAGIC) {
+            return Err(invalid("not an archive"));
+        }
+        match r.u32() {
+            Some(VERSION) => {}
+            Some(v) => return Err(invalid(&format!("unknown version {}", v))),
+            None => return Err(invalid("cut short")),
+        }
+        let count = r.u32().ok_or_else(|| invalid("cut short"))?;
+        let mut entries = HashMap::new();
+        for _ in 0..count {
+            let (name, entry) = r.entry().ok_or_else(|| invalid("index cut short"))?;
+            let end = entry.offset.checked_add(entry.packed);
+            if end.is_none_or(|end| end > data.len()) {
+                return Err(invalid(&format!("'{}' is outside the archive", name)));
+            }
+            entries.insert(name, entry);
+        }
+        Ok(Self {
+            path: path.to_owned(),
+            entries,
+            data,
+        })
+    }
+    // the file's contents, none if it isn't in the archive
+    pub fn read(&self, path: &Path) -> Option<Result<Vec<u8>, Error>> {
+        let entry = self.entries.get(&key(path)?)?;
+        let packed = &self.data[entry.offset..entry.offset + entry.packed];
+        Some(match decompress_to_vec_zlib(packed) {
+            Ok(data) if data.len() == entry.size => Ok(data),
+            _ => Err(Error::InvalidArchive(
+                self.path.clone(),
+                format!("'{}' is corrupt", path.display()),
+            )),
+        })
+    }
+    pub fn contains(&self, path: &Path) -> bool {
+        key(path).is_some_and(|k| self.entries.contains_key(&k))
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        let b = self.data.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(b)
+    }
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+    fn u64(&mut self) -> Option<usize> {
+        let n = u64::from_le_bytes(self.bytes(8)?.try_into().ok()?);
+        n.try_into().ok()
+    }
+    fn entry(&mut self) -> Option<(String, Entry)> {
+        let len = self.u32()? as usize;
+        let name = String::from_utf8(self.bytes(len)?.to_vec()).ok()?;
+        let entry = Entry {
+            offset: self.u64()?,
+            packed: self.u64()?,
+            size: self.u64()?,
+        };
+        Some((name, entry))
+    }
+}
+
+// how a path is written in the index: relative, `/` separated, without
+// `.` and with `..` resolved; none for a path that goes above where it
+// starts or isn't utf-8, as it can't be told apart from others
+fn key(path: &Path) -> Option<String> {
+    let mut parts: Vec<&str> = vec![];
+    for c in path.components() {
+        match c {
+            Component::Normal(p) => parts.push(p.to_str()?),
+            Component::ParentDir => {
+                parts.pop()?;
+            }
+            _ => {}
+        }
+    }
+    Some(parts.join("/"))
+}
+
+// every file under `root`, stored under the path it's found at, so packing
+// `resources` keeps paths like `resources/fox.png` working
+pub fn pack(root: &Path) -> Result<Vec<u8>, Error> {
+    let mut files = vec![];
+    let mut dirs = vec![root.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        let entries = fs::read_dir(&dir).map_err(|e| Error::Io(dir.clone(), e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| Error::Io(dir.clone(), e))?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                let name = key(&path).ok_or_else(|| {
+                    Error::InvalidArchive(path.clone(), "path can't be stored".to_owned())
+                })?;
+                let data = fs::read(&path).map_err(|e| Error::Io(path.clone(), e))?;
+                files.push((name, data));
+            }
+        }
+    }
+    files.sort();
+
+    let packed: Vec<_> = files
+        .iter()
+        .map(|(_, data)| compress_to_vec_zlib(data, COMPRESSION_LEVEL))
+        .collect();
+    let index_len: usize = files.iter().map(|(name, _)| 4 + name.len() + 24).sum();
+    let mut out = Vec::with_capacity(12 + index_len);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&(files.len() as u32).to_le_bytes());
+    let mut offset = 12 + index_len;
+    for ((name, data), blob) in files.iter().zip(&packed) {
+        out.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        out.extend_from_slice(name.as_bytes());
+        for n in [offset, blob.len(), data.len()] {
+            out.extend_from_slice(&(n as u64).to_le_bytes());
+        }
+        offset += blob.len();
+    }
+    for blob in packed {
+        out.extend_from_slice(&blob);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use crate::error::Error;
+
+    use super::{key, pack, Archive};
+
+    #[test]
+    fn test_key() {
+        let key = |p: &str| key(Path::new(p));
+        assert_eq!(key("resources/fox.png").unwrap(), "resources/fox.png");
+        assert_eq!(
+            key("./resources/rooms/../fox.png").unwrap(),
+            "resources/fox.png"
+        );
+        assert_eq!(key("../resources/fox.png"), None);
+        assert_eq!(key("resources/../../fox.png"), None);
+        #[cfg(unix)]
+        {
+            use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+            let latin1 = Path::new(OsStr::from_bytes(b"caf\xe9.png"));
+            assert_eq!(super::key(latin1), None);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = PathBuf::from("resources.pak");
+        let archive = Archive::parse(&path, pack(Path::new("resources/rooms")).unwrap()).unwrap();
+        let start = Path::new("resources/rooms/start.ron");
+        let expected = fs::read(start).unwrap();
+        assert_eq!(archive.read(start).unwrap().unwrap(), expected);
+        assert!(archive.contains(Path::new("resources/rooms/./hall.ron")));
+        assert!(archive.read(Path::new("resources/fox.png")).is_none());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let path = Path::new("resources.pak");
+        let data = pack(Path::new("resources/rooms")).unwrap();
+        for bad in [&b"PK"[..], &data[..20], &data[..data.len() - 1]] {
+            let archive = Archive::parse(path, bad.to_vec());
+            assert!(matches!(archive, Err(Error::InvalidArchive(_, _))));
+        }
+
+        // a flipped byte in start.ron, last of the sorted files
+        let mut data = data;
+        let in_start = data.len() - 3;
+        data[in_start] ^= 0xff;
+        let archive = Archive::parse(path, data).unwrap();
+        let start = archive
+            .read(Path::new("resources/rooms/start.ron"))
+            .unwrap();
+        assert!(matches!(start, Err(Error::InvalidArchive(_, _))));
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    error::Error,
+    image::Image,
+    sheet::{self, Sheet},
+    text::{self, GlyphWriter},
+};
+
+// everything decoded from disk, by path: what's handed out shares the
+// decoded data, so ten copies of an object cost one decode, and `collect`
+// drops whatever only the cache still holds
+#[derive(Debug, Default)]
+pub struct Assets {
+    images: HashMap<PathBuf, Image>,
+    sheets: HashMap<PathBuf, Sheet>,
+    fonts: HashMap<PathBuf, Rc<bdf::Font>>,
+}
+impl Assets {
+    // a png or apng, or an animation out of a sprite sheet, decoded on first
+    // use; every call gets its own animation state
+    pub fn load<T: AsRef<Path>>(&mut self, path: T) -> Result<Image, Error> {
+        let path = path.as_ref();
+        if sheet::is_sheet(path) {
+            let (file, name) = sheet::split(path);
+            let sheet = match self.sheets.entry(file) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => {
+                    let sheet = Sheet::load(e.key())?;
+                    e.insert(sheet)
+                }
+            };
+            return Ok(Image::Animated(sheet.animation(name)?));
+        }
+        if let Some(image) = self.images.get(path) {
+            return Ok(image.clone());
+        }
+        let image = Image::load(path)?;
+        self.images.insert(path.to_owned(), image.clone());
+        Ok(image)
+    }
+    // the game's policy for broken art: complain and carry on with a
+    // placeholder rather than stopping
+    pub fn image<T: AsRef<Path>>(&mut self, path: T) -> Image {
+        self.load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            Image::placeholder()
+        })
+    }
+    pub fn font<T: AsRef<Path>>(&mut self, path: T) -> Result<GlyphWriter, Error> {
+        let path = path.as_ref();
+        let font = match self.fonts.get(path) {
+            Some(font) => Rc::clone(font),
+            None => {
+                let font = Rc::new(text::load_font(path)?);
+                self.fonts.insert(path.to_owned(), Rc::clone(&font));
+                font
+            }
+        };
+        Ok(GlyphWriter::new(path, font))
+    }
+    // drops the cached copies of files that changed on disk so the next load
+    // reads them again, handles already handed out keep the old data
+    pub fn forget(&mut self, files: &[PathBuf]) {
+        self.images.retain(|p, _| !files.contains(p));
+        self.sheets.retain(|_, s| !files.iter().any(|f| s.uses(f)));
+        self.fonts.retain(|p, _| !files.contains(p));
+    }
+    // unloads everything nothing outside the cache refers to any more, e.g.
+    // after leaving a room
+    pub fn collect(&mut self) {
+        self.images.retain(|_, i| i.handles() > 1);
+        self.sheets.retain(|_, s| s.in_use());
+        self.fonts.retain(|_, f| Rc::strong_count(f) > 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::Error, image::Image, text::FONT_PATH};
+
+    use super::Assets;
+
+    #[test]
+    fn test_sharing() {
+        let mut assets = Assets::default();
+        let a = assets.load("resources/ball.png").unwrap();
+        let mut b = assets.load("resources/ball.png").unwrap();
+        assert_eq!(a.handles(), 3);
+
+        // the frames are shared but not where each copy is in them
+        b.animation_mut()
+            .unwrap()
+            .set_position(1, Default::default());
+        assert_eq!(a.animation().unwrap().position().0, 0);
+
+        let walk = assets.load("resources/fox_sheet.json#walk").unwrap();
+        let talk = assets.load("resources/fox_sheet.json#talk").unwrap();
+        assert_eq!(assets.sheets.len(), 1);
+        assert_eq!(walk.handles(), 2);
+        assert_eq!(talk.handles(), 2);
+    }
+
+    #[test]
+    fn test_collect() {
+        let mut assets = Assets::default();
+        let ball = assets.load("resources/ball.png").unwrap();
+        let fox = assets.load("resources/fox.png").unwrap();
+        let walk = assets.load("resources/fox_sheet.json#walk").unwrap();
+        let font = assets.font(FONT_PATH).unwrap();
+        assets.collect();
+        assert_eq!(assets.images.len(), 2);
+        assert_eq!(assets.sheets.len(), 1);
+
+        drop((ball, walk, font));
+        assets.collect();
+        assert_eq!(assets.images.len(), 1);
+        assert!(assets.sheets.is_empty() && assets.fonts.is_empty());
+        assert_eq!(fox.handles(), 2);
+    }
+
+    #[test]
+    fn test_forget() {
+        let mut assets = Assets::default();
+        let ball = assets.load("resources/ball.png").unwrap();
+        let walk = assets.load("resources/fox_sheet.json#walk").unwrap();
+        assets.forget(&[
+            "resources/ball.png".into(),
+            "resources/fox_sheet.png".into(),
+        ]);
+        assert_eq!((ball.handles(), walk.handles()), (1, 1));
+        let reloaded = assets.load("resources/ball.png").unwrap();
+        assert_eq!((ball.handles(), reloaded.handles()), (1, 2));
+        assert_eq!(ball, reloaded);
+    }
+
+    #[test]
+    fn test_placeholder() {
+        let mut assets = Assets::default();
+        let missing = assets.load("resources/missing.png");
+        assert!(matches!(missing, Err(Error::Io(_, _))));
+        assert_eq!(assets.image("resources/missing.png"), Image::placeholder());
+        assert!(assets.images.is_empty());
+    }
+}
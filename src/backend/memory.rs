@@ -0,0 +1,68 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use crate::{buffer::Buffer, error::Error, geometry::Rect};
+
+use super::Backend;
+
+// keeps the last presented frame around, for tests and machines without a gpu
+#[derive(Default, Debug)]
+pub struct MemoryBackend {
+    frame: Vec<u8>,
+    size: Rect,
+}
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn save_png<T: AsRef<Path>>(&self, path: T) -> Result<(), Error> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|e| Error::Io(path.to_owned(), e))?;
+        let (w, h) = self.size.wh();
+        let mut encoder = png::Encoder::new(BufWriter::new(file), w as u32, h as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.frame))
+            .map_err(|e| Error::Encode(path.to_owned(), e))
+    }
+}
+impl Backend for MemoryBackend {
+    fn present(&mut self, buffer: &Buffer) -> Result<(), Error> {
+        self.size = buffer.size();
+        self.frame.clear();
+        self.frame.extend_from_slice(buffer.data());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::{
+        backend::{Backend, MemoryBackend},
+        buffer::Buffer,
+        geometry::{point, rect},
+        image::{Bitmap, Image},
+    };
+
+    #[test]
+    fn test_save_png() {
+        let mut buffer = Buffer::new(rect(8, 6));
+        let bmp = Bitmap::new([255, 0, 0, 255].repeat(4), rect(2, 2));
+        buffer.draw_bmp(&bmp, point(3.0, 2.0));
+
+        let mut backend = MemoryBackend::new();
+        backend.present(&buffer).unwrap();
+
+        let path = env::temp_dir().join("crust_engine_test_save_png.png");
+        backend.save_png(&path).unwrap();
+        let image = Image::load(&path).unwrap();
+        assert_eq!(image.size(), rect(8, 6));
+        let Image::Static(i) = image else {
+            panic!("not static");
+        };
+        assert_eq!(i.data().data(), buffer.data());
+    }
+}
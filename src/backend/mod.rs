@@ -0,0 +1,13 @@
+mod memory;
+mod window;
+
+pub use memory::MemoryBackend;
+pub use window::WindowBackend;
+
+use crate::{buffer::Buffer, error::Error};
+
+// presentation layer: takes a finished frame from the software buffer and
+// puts it somewhere visible
+pub trait Backend {
+    fn present(&mut self, buffer: &Buffer) -> Result<(), Error>;
+}
@@ -0,0 +1,147 @@
+use pixels::{Pixels, PixelsBuilder, SurfaceTexture};
+use winit::{dpi::PhysicalSize, window::Window};
+
+use crate::{
+    buffer::Buffer,
+    config::{Config, Scaling},
+    error::Error,
+    geometry::{point, rect, Point, Rect},
+};
+
+use super::Backend;
+
+// integer scaling is left to `pixels`, stretching is done here: the texture
+// is kept the size of the window and the frame is scaled up into it
+#[derive(Debug)]
+pub struct WindowBackend {
+    pixels: Pixels,
+    size: Rect,
+    scaling: Scaling,
+    window: Rect,
+}
+impl WindowBackend {
+    pub fn new(window: &Window, config: &Config) -> Result<Self, Error> {
+        let size = config.screen();
+        let inner = window.inner_size();
+        let window_size = rect(inner.width as usize, inner.height as usize);
+        let texture = match config.scaling {
+            Scaling::Integer => size,
+            Scaling::Stretch => window_size,
+        };
+        let surface_texture = SurfaceTexture::new(inner.width, inner.height, window);
+        let pixels = PixelsBuilder::new(texture.w as u32, texture.h as u32, surface_texture)
+            .enable_vsync(config.vsync)
+            .build()?;
+        Ok(Self {
+            pixels,
+            size,
+            scaling: config.scaling,
+            window: window_size,
+        })
+    }
+    pub fn resize(&mut self, size: PhysicalSize<u32>) -> Result<(), Error> {
+        // minimised
+        if size.width == 0 || size.height == 0 {
+            return Ok(());
+        }
+        self.pixels.resize_surface(size.width, size.height)?;
+        if self.scaling == Scaling::Stretch {
+            self.pixels.resize_buffer(size.width, size.height)?;
+            self.window = rect(size.width as usize, size.height as usize);
+        }
+        Ok(())
+    }
+    pub fn convert_pos<T: Into<(f32, f32)>>(&self, pos: T) -> Option<Point> {
+        let (x, y) = self.pixels.window_pos_to_pixel(pos.into()).ok()?;
+        match self.scaling {
+            Scaling::Integer => Some(point(x as f64, y as f64)),
+            Scaling::Stretch => {
+                let (scale, offset) = fit(self.size, self.window);
+                let p = point((x as f64 - offset.x) / scale, (y as f64 - offset.y) / scale);
+                let (w, h) = self.size.wh();
+                (p.x >= 0.0 && p.y >= 0.0 && p.x < w as f64 && p.y < h as f64).then_some(p)
+            }
+        }
+    }
+}
+impl Backend for WindowBackend {
+    fn present(&mut self, buffer: &Buffer) -> Result<(), Error> {
+        let frame = self.pixels.get_frame_mut();
+        match self.scaling {
+            Scaling::Integer => frame.copy_from_slice(buffer.data()),
+            Scaling::Stretch => stretch(buffer.data(), buffer.size(), frame, self.window),
+        }
+        self.pixels.render().map_err(|e| e.into())
+    }
+}
+
+// the largest scale `size` fits into `window` at without changing its aspect
+// ratio, and where its top left goes to be centred
+fn fit(size: Rect, window: Rect) -> (f64, Point) {
+    let scale = f64::min(
+        window.w as f64 / size.w as f64,
+        window.h as f64 / size.h as f64,
+    );
+    let offset = point(
+        ((window.w as f64 - size.w as f64 * scale) / 2.0).floor(),
+        ((window.h as f64 - size.h as f64 * scale) / 2.0).floor(),
+    );
+    (scale, offset)
+}
+
+// nearest-neighbour scales `src` into the middle of `dst`, leaving black bars
+// on the sides that don't fit
+fn stretch(src: &[u8], size: Rect, dst: &mut [u8], window: Rect) {
+    let (scale, offset) = fit(size, window);
+    let (left, top) = (offset.x as usize, offset.y as usize);
+    let w = ((size.w as f64 * scale) as usize).min(window.w - left);
+    let h = ((size.h as f64 * scale) as usize).min(window.h - top);
+    dst.fill(0);
+    let columns: Vec<usize> = (0..w)
+        .map(|x| ((x as f64 + 0.5) / scale).min(size.w as f64 - 1.0) as usize)
+        .collect();
+    for y in 0..h {
+        let src_y = ((y as f64 + 0.5) / scale).min(size.h as f64 - 1.0) as usize;
+        let src_row = &src[src_y * size.w * 4..(src_y + 1) * size.w * 4];
+        let start = ((top + y) * window.w + left) * 4;
+        let dst_row = &mut dst[start..start + w * 4];
+        for (pixel, &src_x) in dst_row.chunks_exact_mut(4).zip(&columns) {
+            pixel.copy_from_slice(&src_row[src_x * 4..src_x * 4 + 4]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::{point, rect};
+
+    use super::{fit, stretch};
+
+    #[test]
+    fn test_fit() {
+        assert_eq!(fit(rect(320, 240), rect(640, 480)), (2.0, point(0.0, 0.0)));
+        assert_eq!(fit(rect(320, 240), rect(800, 480)), (2.0, point(80.0, 0.0)));
+        assert_eq!(fit(rect(320, 240), rect(480, 480)), (1.5, point(0.0, 60.0)));
+    }
+
+    #[test]
+    fn test_stretch() {
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let src = [red, blue].concat();
+        let black = [0; 4];
+        let mut dst = vec![9; 4 * 4 * 4];
+        stretch(&src, rect(2, 1), &mut dst, rect(4, 4));
+        let row = |y: usize| dst[y * 16..(y + 1) * 16].to_vec();
+        assert_eq!(row(0), [black; 4].concat());
+        assert_eq!(row(1), [red, red, blue, blue].concat());
+        assert_eq!(row(2), [red, red, blue, blue].concat());
+        assert_eq!(row(3), [black; 4].concat());
+
+        // not a whole multiple
+        let mut dst = vec![9; 5 * 3 * 4];
+        stretch(&src, rect(2, 1), &mut dst, rect(5, 3));
+        assert_eq!(&dst[..20], &[red, red, blue, blue, blue].concat()[..]);
+        assert_eq!(&dst[40..], &[black; 5].concat()[..]);
+    }
+}
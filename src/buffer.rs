@@ -1,85 +1,166 @@
 use std::cmp;
 
-use crate::geometry::{LineSegment, LineType, Point, Rect};
+use crate::geometry::{point, LineSegment, LineType, Point, Rect};
 use crate::image::Bitmap;
-use crate::{error::Error, geometry::rect};
-use pixels::{Pixels, SurfaceTexture};
-use winit::{dpi::PhysicalSize, window::Window};
 
 const POINT_COLOUR: [u8; 4] = [155, 255, 055, 255];
 
+// software framebuffer: everything draws into this and a backend presents it
 #[derive(Debug)]
 pub struct Buffer {
-    data: Pixels,
+    data: Vec<u8>,
     size: Rect,
+    // where the top left of the screen is in the room, draw calls are given
+    // room positions and moved by it
+    camera: Point,
 }
 impl Buffer {
-    pub fn new(window: &Window) -> Self {
-        let (w, h) = (640, 465);
-        let surface_texture = SurfaceTexture::new(w, h, &window);
-        let pixels = Pixels::new(w, h, surface_texture).unwrap();
+    pub fn new(size: Rect) -> Self {
+        let (w, h) = size.wh();
         Self {
-            data: pixels,
-            size: rect(w as usize, h as usize),
+            data: vec![0; w * h * 4],
+            size,
+            camera: point(0.0, 0.0),
         }
     }
-    pub fn draw_raw_slice(&mut self, data: &[u8]) {
-        let buffer = self.data.get_frame_mut();
-        buffer.copy_from_slice(data)
+    pub fn size(&self) -> Rect {
+        self.size
+    }
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    // the origin for drawing in screen positions, e.g. the user interface
+    pub fn set_camera(&mut self, camera: Point) {
+        self.camera = camera;
+    }
+    pub fn camera(&self) -> Point {
+        self.camera
+    }
+    pub fn clear(&mut self) {
+        self.data.fill(0);
+    }
+    // where a position ends up on the screen, in whole pixels
+    fn screen_pos(&self, pos: Point) -> (i64, i64) {
+        (
+            (pos.x - self.camera.x).floor() as i64,
+            (pos.y - self.camera.y).floor() as i64,
+        )
+    }
+    // copies an opaque background without blending, clearing whatever it
+    // doesn't cover
+    pub fn draw_opaque(&mut self, bmp: &Bitmap) {
+        self.clear();
+        let (x, y) = self.screen_pos(point(0.0, 0.0));
+        let (size_w, size_h) = self.size.wh();
+        let left = (-x).max(0) as usize;
+        let right = (bmp.cols() as i64).min(size_w as i64 - x);
+        if right <= left as i64 {
+            return;
+        }
+        let right = right as usize;
+        for row in 0..size_h {
+            let src_y = row as i64 - y;
+            if src_y < 0 || src_y >= bmp.rows() as i64 {
+                continue;
+            }
+            let src = &bmp.row_partial(src_y as usize, right)[left * 4..];
+            let start = (row * size_w + (left as i64 + x) as usize) * 4;
+            self.data[start..start + src.len()].copy_from_slice(src);
+        }
+    }
+    // darkens everything drawn so far, 0 leaves it as is and 1 is black
+    pub fn fade(&mut self, level: f64) {
+        let keep = 1.0 - level.clamp(0.0, 1.0);
+        for pixel in self.data.chunks_exact_mut(4) {
+            for c in &mut pixel[..3] {
+                *c = (*c as f64 * keep).round() as u8;
+            }
+        }
     }
     pub fn draw_bmp<T: Into<Point>>(&mut self, bmp: &Bitmap, pos: T) {
-        let pos = pos.into();
-        let buffer = self.data.get_frame_mut();
+        let (x, y) = self.screen_pos(pos.into());
+        let buffer = &mut self.data;
 
         // clipping
         let (size_w, size_h) = self.size.wh();
-        let (pos_x, pos_y) = (pos.x as usize, pos.y as usize);
-        let rows = cmp::min(bmp.rows(), size_h - pos_y);
-        let cols = cmp::min(bmp.cols(), size_w - pos_x);
+        let (skip_x, skip_y) = ((-x).max(0) as usize, (-y).max(0) as usize);
+        let rows = (bmp.rows() as i64).min(size_h as i64 - y);
+        let cols = (bmp.cols() as i64).min(size_w as i64 - x);
+        if rows <= skip_y as i64 || cols <= skip_x as i64 {
+            return;
+        }
 
         // draw
-        for rownum in 0..rows {
-            let lstart = (pos_y + rownum) * (size_w * 4) + (pos_x * 4);
-            for (i, pixel) in bmp.row_partial(rownum, cols).chunks_exact(4).enumerate() {
+        let left = (x + skip_x as i64) as usize;
+        for rownum in skip_y..rows as usize {
+            let lstart = ((y + rownum as i64) as usize * size_w + left) * 4;
+            let row = &bmp.row_partial(rownum, cols as usize)[skip_x * 4..];
+            for (i, pixel) in row.chunks_exact(4).enumerate() {
                 let idx = lstart + (i * 4);
                 let base = &mut buffer[idx..idx + 4];
                 composit_pixel(base, pixel);
             }
         }
     }
-    pub fn resize(&mut self, size: PhysicalSize<u32>) -> Result<(), Error> {
-        self.data
-            .resize_surface(size.width, size.height)
-            .map_err(|e| e.into())
-    }
-    pub fn render(&self) {
-        self.data.render().unwrap();
-    }
-    pub fn convert_pos<T: Into<(f32, f32)>>(&self, pos: T) -> Option<Point> {
-        self.data
-            .window_pos_to_pixel(pos.into())
-            .ok()
-            .map(|p| p.into())
+    // nearest-neighbour scaled blit, optionally flipped left to right; `pos`
+    // is where the scaled top left goes
+    pub fn draw_bmp_scaled<T: Into<Point>>(
+        &mut self,
+        bmp: &Bitmap,
+        pos: T,
+        scale: f64,
+        mirrored: bool,
+    ) {
+        if scale == 1.0 && !mirrored {
+            return self.draw_bmp(bmp, pos);
+        }
+        let (x, y) = self.screen_pos(pos.into());
+        let buffer = &mut self.data;
+        let w = (bmp.cols() as f64 * scale).round() as i64;
+        let h = (bmp.rows() as f64 * scale).round() as i64;
+
+        // clipping
+        let (size_w, size_h) = self.size.wh();
+        let (skip_x, skip_y) = ((-x).max(0), (-y).max(0));
+        let rows = h.min(size_h as i64 - y);
+        let cols = w.min(size_w as i64 - x);
+
+        // draw
+        for rownum in skip_y..rows {
+            let src_y = cmp::min(((rownum as f64 + 0.5) / scale) as usize, bmp.rows() - 1);
+            let lstart = ((y + rownum) as usize * size_w) as i64 + x;
+            for col in skip_x..cols {
+                let mut src_x = cmp::min(((col as f64 + 0.5) / scale) as usize, bmp.cols() - 1);
+                if mirrored {
+                    src_x = bmp.cols() - 1 - src_x;
+                }
+                let idx = (lstart + col) as usize * 4;
+                composit_pixel(&mut buffer[idx..idx + 4], bmp.pixel(src_x, src_y));
+            }
+        }
     }
     pub fn draw_line(&mut self, l: &LineSegment, t: LineType) {
-        let buffer = self.data.get_frame_mut();
-        let points = l.points();
-        for p in points {
-            let (px, py) = p.xy();
-            let pidx = py * (self.size.wh().0 * 4) + (px * 4);
-            buffer[pidx..pidx + 4].copy_from_slice(t.colour());
+        for p in l.points() {
+            let (px, py) = self.screen_pos(p);
+            self.put(px, py, t.colour());
         }
     }
     pub fn draw_point(&mut self, p: Point) {
-        let buffer = self.data.get_frame_mut();
-        let (px, py) = p.xy();
-        for x in cmp::max(px, 2) - 2..px + 2 {
-            for y in cmp::max(py, 2) - 2..py + 1 {
-                let pidx = y * (self.size.wh().0 * 4) + x * 4;
-                buffer[pidx..pidx + 4].copy_from_slice(&POINT_COLOUR);
+        let (px, py) = self.screen_pos(p);
+        for x in px - 2..px + 2 {
+            for y in py - 2..py + 1 {
+                self.put(x, y, &POINT_COLOUR);
             }
         }
     }
+    // sets a pixel on the screen, if it's on the screen
+    fn put(&mut self, x: i64, y: i64, colour: &[u8]) {
+        let (w, h) = self.size.wh();
+        if x >= 0 && y >= 0 && (x as usize) < w && (y as usize) < h {
+            let i = (y as usize * w + x as usize) * 4;
+            self.data[i..i + 4].copy_from_slice(colour);
+        }
+    }
 }
 #[inline(always)]
 fn composit_pixel(dest: &mut [u8], src: &[u8]) {
@@ -94,7 +175,11 @@ fn composit_pixel(dest: &mut [u8], src: &[u8]) {
 
 #[cfg(test)]
 mod tests {
-    use crate::buffer::composit_pixel;
+    use crate::{
+        buffer::{composit_pixel, Buffer, POINT_COLOUR},
+        geometry::{point, rect},
+        image::Bitmap,
+    };
 
     #[test]
     fn test_composit() {
@@ -104,4 +189,70 @@ mod tests {
         composit_pixel(&mut bg, &obj);
         assert_eq!(bg, res);
     }
+
+    fn pixel_at(buffer: &Buffer, x: usize, y: usize) -> &[u8] {
+        let i = (y * buffer.size().w + x) * 4;
+        &buffer.data()[i..i + 4]
+    }
+
+    #[test]
+    fn test_draw_bmp_scaled() {
+        let mut buffer = Buffer::new(rect(4, 4));
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let bmp = Bitmap::new([red, blue].concat(), rect(2, 1));
+        buffer.draw_bmp_scaled(&bmp, point(0.0, 1.0), 2.0, false);
+        assert_eq!(pixel_at(&buffer, 0, 0), &[0, 0, 0, 0]);
+        assert_eq!(pixel_at(&buffer, 1, 1), &red);
+        assert_eq!(pixel_at(&buffer, 2, 2), &blue);
+        assert_eq!(pixel_at(&buffer, 3, 3), &[0, 0, 0, 0]);
+
+        let mut buffer = Buffer::new(rect(4, 4));
+        buffer.draw_bmp_scaled(&bmp, point(3.0, 3.0), 0.5, false);
+        assert_eq!(&buffer.data()[60..64], &blue);
+
+        let mut buffer = Buffer::new(rect(4, 4));
+        buffer.draw_bmp_scaled(&bmp, point(0.0, 0.0), 1.0, true);
+        assert_eq!(pixel_at(&buffer, 0, 0), &blue);
+        assert_eq!(pixel_at(&buffer, 1, 0), &red);
+    }
+
+    #[test]
+    fn test_camera() {
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let bmp = Bitmap::new([red, blue, blue, red].concat(), rect(2, 2));
+
+        // partly off the top left of the screen
+        let mut buffer = Buffer::new(rect(4, 4));
+        buffer.set_camera(point(5.0, 5.0));
+        buffer.draw_bmp(&bmp, point(4.0, 4.0));
+        assert_eq!(pixel_at(&buffer, 0, 0), &red);
+        assert_eq!(pixel_at(&buffer, 1, 0), &[0, 0, 0, 0]);
+        buffer.draw_bmp_scaled(&bmp, point(4.0, 7.0), 1.0, true);
+        assert_eq!(pixel_at(&buffer, 0, 2), &red);
+        buffer.draw_bmp_scaled(&bmp, point(0.0, 0.0), 2.0, false);
+        buffer.draw_bmp(&bmp, point(-10.0, 20.0));
+        buffer.draw_point(point(5.0, 5.0));
+        assert_eq!(pixel_at(&buffer, 1, 0), &POINT_COLOUR);
+
+        let background = Bitmap::new([red, blue].concat().repeat(3), rect(6, 1));
+        let mut buffer = Buffer::new(rect(4, 2));
+        buffer.set_camera(point(3.0, 0.0));
+        buffer.draw_opaque(&background);
+        assert_eq!(pixel_at(&buffer, 0, 0), &blue);
+        assert_eq!(pixel_at(&buffer, 1, 0), &red);
+        assert_eq!(pixel_at(&buffer, 3, 0), &[0, 0, 0, 0]);
+        assert_eq!(pixel_at(&buffer, 0, 1), &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_fade() {
+        let mut buffer = Buffer::new(rect(1, 1));
+        buffer.draw_opaque(&Bitmap::new(vec![200, 100, 50, 255], rect(1, 1)));
+        buffer.fade(0.5);
+        assert_eq!(buffer.data(), &[100, 50, 25, 255]);
+        buffer.fade(1.0);
+        assert_eq!(buffer.data(), &[0, 0, 0, 255]);
+    }
 }
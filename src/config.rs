@@ -0,0 +1,182 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{error::Error, game_state::SCREEN, geometry::Rect};
+
+pub const CONFIG_PATH: &str = "config.ron";
+const ARCHIVE_PATH: &str = "resources.pak";
+
+// how the logical screen is blown up to fill the window
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+pub enum Scaling {
+    // whole multiples only, keeping every pixel square and the same size
+    #[default]
+    Integer,
+    // as large as fits, keeping the aspect ratio
+    Stretch,
+}
+
+// display settings, read from `config.ron` and overridden on the command line
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // the size of the screen the game draws, in its own pixels
+    pub resolution: (usize, usize),
+    pub fullscreen: bool,
+    pub scaling: Scaling,
+    pub vsync: bool,
+    // watch `resources/` and swap changed files into the running game
+    pub hot_reload: bool,
+    // packed resources, read in place of the loose files when the file exists
+    // and hot reload is off
+    pub archive: PathBuf,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            resolution: SCREEN.wh(),
+            fullscreen: true,
+            scaling: Scaling::Integer,
+            vsync: true,
+            hot_reload: false,
+            archive: ARCHIVE_PATH.into(),
+        }
+    }
+}
+impl Config {
+    // a missing file leaves everything at the defaults
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let s = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(Error::Io(path.to_owned(), e)),
+        };
+        let config: Self = ron::from_str(&s)
+            .map_err(|e| Error::InvalidConfig(format!("{}: {}", path.display(), e)))?;
+        config.validate()?;
+        Ok(config)
+    }
+    // reads the file named by `--config`, or the default one, and applies the
+    // rest of the flags on top; returns the arguments it doesn't know
+    pub fn from_args(args: &[String]) -> Result<(Self, Vec<String>), Error> {
+        let path = match args.iter().position(|a| a == "--config") {
+            Some(i) => args
+                .get(i + 1)
+                .map(String::as_str)
+                .ok_or_else(|| Error::InvalidConfig("--config needs a path".to_owned()))?,
+            None => CONFIG_PATH,
+        };
+        let mut config = Self::load(path)?;
+        let mut rest = vec![];
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => {
+                    args.next();
+                }
+                "--resolution" => {
+                    let value = args.next().map(String::as_str).unwrap_or_default();
+                    config.resolution = parse_resolution(value)?;
+                }
+                "--windowed" => config.fullscreen = false,
+                "--fullscreen" => config.fullscreen = true,
+                "--integer" => config.scaling = Scaling::Integer,
+                "--stretch" => config.scaling = Scaling::Stretch,
+                "--vsync" => config.vsync = true,
+                "--no-vsync" => config.vsync = false,
+                "--hot-reload" => config.hot_reload = true,
+                "--archive" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| Error::InvalidConfig("--archive needs a path".to_owned()))?;
+                    config.archive = value.into();
+                }
+                _ => rest.push(arg.clone()),
+            }
+        }
+        config.validate()?;
+        Ok((config, rest))
+    }
+    pub fn screen(&self) -> Rect {
+        let (w, h) = self.resolution;
+        Rect { w, h }
+    }
+    fn validate(&self) -> Result<(), Error> {
+        match self.resolution {
+            (0, _) | (_, 0) => Err(Error::InvalidConfig(format!(
+                "resolution {}x{} is empty",
+                self.resolution.0, self.resolution.1
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+// `WIDTHxHEIGHT`, e.g. `320x240`
+fn parse_resolution(s: &str) -> Result<(usize, usize), Error> {
+    let invalid = || Error::InvalidConfig(format!("'{}' is not a resolution like 640x480", s));
+    let (w, h) = s.split_once('x').ok_or_else(invalid)?;
+    Ok((
+        w.parse().map_err(|_| invalid())?,
+        h.parse().map_err(|_| invalid())?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::Error, game_state::SCREEN};
+
+    use super::{Config, Scaling};
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn test_load() {
+        let config = Config::load("resources/missing.ron").unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!(config.screen(), SCREEN);
+
+        let (config, rest) = Config::from_args(&args("--config config.ron")).unwrap();
+        assert_eq!(config.resolution, (640, 465));
+        assert_eq!(config.archive, Config::default().archive);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_args() {
+        let (config, rest) = Config::from_args(&args(
+            "--config resources/missing.ron --windowed --resolution 320x240 --stretch --no-vsync --hot-reload --archive game.pak --screenshot out.png",
+        ))
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                resolution: (320, 240),
+                fullscreen: false,
+                scaling: Scaling::Stretch,
+                vsync: false,
+                hot_reload: true,
+                archive: "game.pak".into(),
+            }
+        );
+        assert_eq!(rest, args("--screenshot out.png"));
+
+        for bad in [
+            "--resolution 320",
+            "--resolution 0x240",
+            "--resolution",
+            "--config",
+            "--archive",
+        ] {
+            let result = Config::from_args(&args(bad));
+            assert!(matches!(result, Err(Error::InvalidConfig(_))), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::{error::Error, image::Control, scene, scene::Coord, script::Host};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum Step {
+    // walks the actor along the walkbox graph to the point
+    Walk(String, Coord),
+    // milliseconds
+    Wait(u64),
+    Say(String, String),
+    // swaps in another image for the actor
+    Switch(String, PathBuf),
+    // like a switch, but waits for animations that play once to finish
+    Play(String, PathBuf, Control),
+    // to a level between 0 (clear) and 1 (black) over some milliseconds
+    Fade(f64, u64),
+}
+
+// a cutscene as written in a file: a list of steps played one after the other
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Timeline {
+    pub steps: Vec<Step>,
+}
+impl Timeline {
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let timeline: Self = scene::load(path)?;
+        timeline.validate(path)?;
+        Ok(timeline)
+    }
+    fn validate(&self, path: &Path) -> Result<(), Error> {
+        for step in &self.steps {
+            match step {
+                Step::Switch(_, image) | Step::Play(_, image, _) => {
+                    scene::asset(image)?;
+                }
+                Step::Fade(level, _) if !(0.0..=1.0).contains(level) => {
+                    return Err(Error::InvalidScene(
+                        path.to_owned(),
+                        format!("fade level {} is outside 0..1", level),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+// a timeline being played
+#[derive(Debug)]
+pub struct Cutscene {
+    timeline: Timeline,
+    index: usize,
+    started: bool,
+    elapsed: Duration,
+    // the fade level the current fade step started from
+    fade_from: f64,
+}
+impl Cutscene {
+    pub fn new(timeline: Timeline) -> Self {
+        Self {
+            timeline,
+            index: 0,
+            started: false,
+            elapsed: Duration::ZERO,
+            fade_from: 0.0,
+        }
+    }
+    // plays until a step has to wait for later ticks, returns false once done
+    pub fn tick<H: Host>(&mut self, host: &mut H, dt: Duration) -> bool {
+        self.elapsed += dt;
+        while let Some(step) = self.timeline.steps.get(self.index) {
+            let first = !self.started;
+            if first {
+                self.started = true;
+                self.elapsed = Duration::ZERO;
+                self.fade_from = host.fade();
+            }
+            match step {
+                Step::Walk(actor, to) => {
+                    let started = !first || host.walk(actor, (*to).into());
+                    if started && host.is_walking(actor) {
+                        return true;
+                    }
+                }
+                Step::Wait(ms) => {
+                    if self.elapsed < Duration::from_millis(*ms) {
+                        return true;
+                    }
+                }
+                Step::Say(actor, text) => {
+                    let started = !first || host.say(actor, text);
+                    if started && host.is_talking() {
+                        return true;
+                    }
+                }
+                Step::Switch(actor, image) => host.play(actor, image, Control::default()),
+                Step::Play(actor, image, control) => {
+                    if first {
+                        host.play(actor, image, *control);
+                    }
+                    if host.is_animating(actor) {
+                        return true;
+                    }
+                }
+                Step::Fade(level, ms) => {
+                    let t = match ms {
+                        0 => 1.0,
+                        ms => (self.elapsed.as_millis() as f64 / *ms as f64).min(1.0),
+                    };
+                    host.set_fade(self.fade_from + (level - self.fade_from) * t);
+                    if t < 1.0 {
+                        return true;
+                    }
+                }
+            }
+            self.index += 1;
+            self.started = false;
+        }
+        false
+    }
+    // jumps straight to the end, leaving the world as if every remaining
+    // step had played out
+    pub fn skip<H: Host>(&mut self, host: &mut H) {
+        for step in &self.timeline.steps[self.index.min(self.timeline.steps.len())..] {
+            match step {
+                Step::Walk(actor, to) => host.place(actor, (*to).into()),
+                Step::Wait(_) | Step::Say(_, _) => {}
+                Step::Switch(actor, image) => host.play(actor, image, Control::default()),
+                Step::Play(actor, image, control) => {
+                    host.play(actor, image, *control);
+                    host.finish_animation(actor);
+                }
+                Step::Fade(level, _) => host.set_fade(*level),
+            }
+        }
+        self.index = self.timeline.steps.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{error::Error, scene};
+
+    use super::{Step, Timeline};
+
+    #[test]
+    fn test_load_timeline() {
+        let timeline = Timeline::load("resources/cutscenes/fetch.ron").unwrap();
+        assert_eq!(timeline.steps[2], Step::Fade(0.7, 400));
+
+        let path = Path::new("test.ron");
+        let s = r#"(steps: [Switch("fennec", "resources/missing.png")])"#;
+        let timeline: Timeline = scene::parse(path, s).unwrap();
+        assert!(matches!(timeline.validate(path), Err(Error::Io(_, _))));
+        let s = r#"(steps: [Fade(1.5, 100)])"#;
+        let timeline: Timeline = scene::parse(path, s).unwrap();
+        let res = timeline.validate(path);
+        assert!(matches!(res, Err(Error::InvalidScene(_, _))));
+    }
+}
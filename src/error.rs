@@ -1,8 +1,24 @@
+use std::{fmt, io, path::PathBuf};
+
 use pixels::TextureError;
+use png::EncodingError;
 
 #[derive(Debug)]
 pub enum Error {
     ResizeError,
+    Render,
+    Io(PathBuf, io::Error),
+    // a png or font that can't be read
+    Decode(PathBuf, String),
+    MissingGlyph(PathBuf, char),
+    // an apng whose animation or frame control chunks don't add up
+    InvalidAnimation(PathBuf, String),
+    InvalidScene(PathBuf, String),
+    InvalidSave(PathBuf, String),
+    InvalidConfig(String),
+    // a packed resource file that's truncated or damaged
+    InvalidArchive(PathBuf, String),
+    Encode(PathBuf, EncodingError),
 }
 
 impl From<TextureError> for Error {
@@ -10,3 +26,37 @@ impl From<TextureError> for Error {
         Self::ResizeError
     }
 }
+
+impl From<pixels::Error> for Error {
+    fn from(_: pixels::Error) -> Self {
+        Self::Render
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ResizeError => write!(f, "failed to resize the surface"),
+            Self::Render => write!(f, "failed to render the frame"),
+            Self::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            Self::Decode(path, reason) => write!(f, "{}: {}", path.display(), reason),
+            Self::MissingGlyph(path, c) => {
+                write!(f, "{}: no glyph for {:?}", path.display(), c)
+            }
+            Self::InvalidAnimation(path, reason) => {
+                write!(f, "{}: invalid animation: {}", path.display(), reason)
+            }
+            Self::InvalidScene(path, reason) => {
+                write!(f, "{}: invalid scene data: {}", path.display(), reason)
+            }
+            Self::InvalidSave(path, reason) => {
+                write!(f, "{}: invalid save game: {}", path.display(), reason)
+            }
+            Self::InvalidConfig(reason) => write!(f, "invalid config: {}", reason),
+            Self::InvalidArchive(path, reason) => {
+                write!(f, "{}: invalid archive: {}", path.display(), reason)
+            }
+            Self::Encode(path, e) => write!(f, "{}: {}", path.display(), e),
+        }
+    }
+}
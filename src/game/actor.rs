@@ -1,75 +1,206 @@
-use std::{path::Path, time::Duration};
+use std::time::Duration;
 
 use crate::{
     buffer::Buffer,
-    geometry::{point, Point},
-    image::Image,
+    geometry::{point, vector, Point},
+    image::{AnimatedImage, Image, Playback},
+    save::{ActorState, FrameState},
 };
 
-use super::{update_location, Updatable};
+use super::{
+    animation::{AnimationSet, Direction, Pose},
+    update_location, Interaction, Updatable,
+};
 
 #[derive(Debug, PartialEq)]
 pub struct Actor {
+    // drawn when there's no animation for what the actor is doing
     pub image: Image,
+    pub animations: AnimationSet,
+    pub facing: Direction,
+    pub talking: bool,
+    // the animation being played and whether it's mirrored
+    current: Option<(Pose, Direction, bool)>,
+    // what a script asked the actor to play, shown over the animations until
+    // it has played through once
+    played: Option<Image>,
     pub location: Point,
     pub path: Vec<Point>,
     pub movement_speed: Option<f64>,
+    pub interaction: Option<Interaction>,
+    pub z: Option<f64>,
+    // set from the walkbox every tick, shrinks the actor and slows it down
+    pub scale: f64,
+    hovered: bool,
 }
 impl Actor {
-    pub fn new<T: AsRef<Path>>(path: T, loc: Point, ms: Option<f64>) -> Self {
-        let image = Image::load(path);
+    pub fn new(image: Image, loc: Point, ms: Option<f64>) -> Self {
         Self {
             image,
+            animations: AnimationSet::default(),
+            facing: Direction::S,
+            talking: false,
+            current: None,
+            played: None,
             location: loc,
             path: vec![],
             movement_speed: ms,
+            interaction: None,
+            z: None,
+            scale: 1.0,
+            hovered: false,
+        }
+    }
+    pub fn current_image(&self) -> &Image {
+        if let Some(image) = &self.played {
+            return image;
+        }
+        self.current
+            .and_then(|(pose, direction, _)| self.animations.get(pose, direction))
+            .unwrap_or(&self.image)
+    }
+    pub fn current_image_mut(&mut self) -> &mut Image {
+        let Self {
+            played,
+            animations,
+            current,
+            image,
+            ..
+        } = self;
+        let posed = current.and_then(|(pose, direction, _)| animations.get_mut(pose, direction));
+        match (played, posed) {
+            (Some(played), _) => played,
+            (None, Some(posed)) => posed,
+            (None, None) => image,
         }
     }
+    fn pose_image_mut(&mut self) -> &mut Image {
+        match self.current {
+            Some((pose, direction, _)) => match self.animations.get_mut(pose, direction) {
+                Some(image) => image,
+                None => &mut self.image,
+            },
+            None => &mut self.image,
+        }
+    }
+    // shows `image` in place of the actor's animations; one played once goes
+    // back to them when it's done, anything else stays
+    pub fn play(&mut self, image: Image) {
+        self.played = Some(image);
+    }
+    // what the draw order is sorted by: further down the screen is in front
+    pub fn baseline(&self) -> f64 {
+        self.z.unwrap_or(self.location.y)
+    }
+    pub fn is_hovered(&self) -> bool {
+        self.hovered
+    }
+    // where the character stands to talk to this actor: next to it by default
+    pub fn interaction_point(&self) -> Point {
+        let s = self.current_image().size();
+        self.interaction
+            .as_ref()
+            .and_then(|i| i.walk_to)
+            .unwrap_or_else(|| self.location + vector(s.w as f64 * self.scale, 0.0))
+    }
+    // only actors that can be interacted with are hit by the mouse
+    fn contains(&self, p: Point) -> bool {
+        let s = self.current_image().size();
+        let (w, h) = (s.w as f64 * self.scale, s.h as f64 * self.scale);
+        self.interaction.is_some()
+            && p.x >= self.location.x - w / 2.0
+            && p.x < self.location.x + w / 2.0
+            && p.y >= self.location.y - h
+            && p.y < self.location.y
+    }
     pub fn set_path(&mut self, path: impl Iterator<Item = Point>) {
         self.path = path.map(|e| e.to_owned()).collect();
     }
+    pub fn state(&self) -> ActorState {
+        ActorState {
+            name: self.interaction.as_ref().map(|i| i.name.clone()),
+            location: (self.location.x, self.location.y),
+            path: self.path.iter().map(|p| (p.x, p.y)).collect(),
+            frame: FrameState::of(self.current_image()),
+        }
+    }
+    pub fn restore(&mut self, state: &ActorState) {
+        self.location = state.location.into();
+        self.path = state.path.iter().map(|&p| p.into()).collect();
+        FrameState::apply(state.frame, self.current_image_mut());
+    }
 }
 impl Updatable for Actor {
-    fn mouse_over(&mut self, _p: Point) {
-        //
+    fn mouse_over(&mut self, p: Point) {
+        self.hovered = self.contains(p);
     }
-    fn mouse_click(&mut self, _p: Point) {
-        // self.set_path(p);
+    fn mouse_click(&mut self, p: Point) -> bool {
+        self.contains(p)
     }
     fn tick(&mut self, dt: Duration) {
-        if let Image::Animated(img) = &mut self.image {
-            img.update(dt);
-        }
-        if self.movement_speed.is_none() {
-            return;
-        }
-        if let Some(next) = self.path.first() {
-            self.location = update_location(self.location, next, self.movement_speed.unwrap(), dt);
+        let from = self.location;
+        if let (Some(next), Some(speed)) = (self.path.first(), self.movement_speed) {
+            self.location = update_location(self.location, next, speed * self.scale, dt);
             if next == &self.location {
                 self.path.remove(0);
             }
         }
+
+        if let Some(direction) = Direction::of(self.location - from) {
+            self.facing = direction;
+        }
+        let pose = match (self.location != from, self.talking) {
+            (true, _) => Pose::Walk,
+            (false, true) => Pose::Talk,
+            (false, false) => Pose::Idle,
+        };
+        let current = self.animations.resolve(pose, self.facing);
+        if current != self.current {
+            self.current = current;
+            if let Some(a) = self.pose_image_mut().animation_mut() {
+                a.restart();
+            }
+        }
+        if let Some(a) = self.current_image_mut().animation_mut() {
+            a.update(dt);
+        }
+        let done = |a: &AnimatedImage| a.is_finished() && a.playback() == Playback::Once;
+        if self
+            .played
+            .as_ref()
+            .and_then(Image::animation)
+            .is_some_and(done)
+        {
+            self.played = None;
+        }
     }
     fn draw(&self, buf: &mut Buffer) {
-        let s = self.image.size();
-        let offset_location = self.location - point(s.w as f64 / 2.0, s.h as f64);
-        self.image.draw(buf, offset_location.into());
+        let image = self.current_image();
+        let mirrored = self.played.is_none() && self.current.is_some_and(|(_, _, m)| m);
+        let s = image.size();
+        let (w, h) = (s.w as f64 * self.scale, s.h as f64 * self.scale);
+        let offset_location = self.location - point(w / 2.0, h);
+        image.draw_scaled(buf, offset_location.into(), self.scale, mirrored);
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
+    use std::{collections::HashMap, time::Duration};
 
     use crate::{
-        game::{Actor, Updatable},
-        geometry::point,
-        image::Image,
+        assets::Assets,
+        game::{
+            animation::{AnimationSet, Direction, Pose},
+            Actor, Updatable,
+        },
+        geometry::{point, rect},
+        image::{Control, Image, Playback},
     };
 
     #[test]
     fn test_sprite_destination() {
-        let image = "resources/fox.png";
+        let image = Image::load("resources/fox.png").unwrap();
         let mut sprite = Actor::new(image, point(0.0, 0.0), None);
         let dest = vec![point(10.0, 10.0)];
 
@@ -80,13 +211,22 @@ mod tests {
 
     #[test]
     fn test_sprite_movement() {
-        let image = Image::load("resources/fox.png");
+        let image = Image::load("resources/fox.png").unwrap();
         let dt = Duration::from_secs_f64(0.1);
         let mut sprite = Actor {
             image,
+            animations: AnimationSet::default(),
+            facing: Direction::S,
+            talking: false,
+            current: None,
+            played: None,
             location: point(0.0, 0.0),
             path: vec![point(10.0, 10.0)],
             movement_speed: Some(0.05),
+            interaction: None,
+            z: None,
+            scale: 1.0,
+            hovered: false,
         };
         sprite.tick(dt);
 
@@ -95,4 +235,68 @@ mod tests {
             point(3.5355339059327373, 3.5355339059327373)
         );
     }
+
+    #[test]
+    fn test_scaled_movement() {
+        let image = Image::load("resources/fox.png").unwrap();
+        let mut sprite = Actor::new(image, point(0.0, 0.0), Some(0.05));
+        sprite.scale = 0.5;
+        sprite.set_path(vec![point(0.0, 100.0)].into_iter());
+        sprite.tick(Duration::from_millis(100));
+        assert_eq!(sprite.location, point(0.0, 2.5));
+    }
+
+    #[test]
+    fn test_directional_animation() {
+        let image = Image::load("resources/fox.png").unwrap();
+        let mut sprite = Actor::new(image, point(100.0, 0.0), Some(0.05));
+        let walk = HashMap::from([(Direction::E, "resources/fox_walk.png".into())]);
+        let data = HashMap::from([(Pose::Walk, walk)]);
+        sprite.animations = AnimationSet::load(&data, &mut Assets::default()).unwrap();
+        let dt = Duration::from_millis(100);
+
+        sprite.tick(dt);
+        assert_eq!(sprite.current, None);
+        sprite.set_path(vec![point(0.0, 0.0)].into_iter());
+        sprite.tick(dt);
+        assert_eq!(sprite.facing, Direction::W);
+        assert_eq!(sprite.current, Some((Pose::Walk, Direction::E, true)));
+        assert!(sprite.current_image().animation().is_some());
+
+        // back to the plain image once the path runs out
+        (0..20).for_each(|_| sprite.tick(dt));
+        assert!(sprite.path.is_empty());
+        assert_eq!(sprite.current, None);
+        assert!(sprite.current_image().animation().is_none());
+    }
+
+    #[test]
+    fn test_play() {
+        let image = Image::load("resources/fox.png").unwrap();
+        let mut sprite = Actor::new(image, point(100.0, 0.0), Some(0.05));
+        let walk = HashMap::from([(Direction::E, "resources/fox_walk.png".into())]);
+        let data = HashMap::from([(Pose::Walk, walk)]);
+        sprite.animations = AnimationSet::load(&data, &mut Assets::default()).unwrap();
+        let dt = Duration::from_millis(100);
+
+        let Image::Animated(mut pickup) = Image::load("resources/ball.png").unwrap() else {
+            panic!("not animated");
+        };
+        pickup.play(Control {
+            playback: Playback::Once,
+            ..Control::default()
+        });
+        sprite.play(Image::Animated(pickup));
+        sprite.set_path(vec![point(90.0, 0.0)].into_iter());
+        sprite.tick(dt);
+        assert_eq!(sprite.current_image().size(), rect(100, 100));
+        assert!(sprite.current_image().animation().unwrap().is_running());
+
+        // the walk comes back once it has played through
+        sprite.set_path(vec![point(-500.0, 0.0)].into_iter());
+        (0..20).for_each(|_| sprite.tick(dt));
+        assert_eq!(sprite.current, Some((Pose::Walk, Direction::E, true)));
+        let walk = sprite.current_image().animation().unwrap();
+        assert_eq!(walk.playback(), Playback::Loop);
+    }
 }
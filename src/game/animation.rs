@@ -0,0 +1,117 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::Deserialize;
+
+use crate::{assets::Assets, error::Error, geometry::Vector, image::Image};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum Direction {
+    N,
+    E,
+    S,
+    W,
+}
+impl Direction {
+    // the direction a step points in, favouring east and west on diagonals
+    pub fn of(v: Vector) -> Option<Self> {
+        if v.x == 0.0 && v.y == 0.0 {
+            None
+        } else if v.x.abs() >= v.y.abs() {
+            Some(if v.x > 0.0 { Self::E } else { Self::W })
+        } else {
+            Some(if v.y > 0.0 { Self::S } else { Self::N })
+        }
+    }
+    // only east and west can be drawn by flipping the other one
+    fn mirror(&self) -> Option<Self> {
+        match self {
+            Self::E => Some(Self::W),
+            Self::W => Some(Self::E),
+            Self::N | Self::S => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum Pose {
+    Idle,
+    Walk,
+    Talk,
+}
+
+// as written in scene files: `{ Walk: { E: "walk_east.png" }, ... }`
+pub type AnimationData = HashMap<Pose, HashMap<Direction, PathBuf>>;
+
+// an actor's animations by what it's doing and which way it's facing
+#[derive(Debug, Default, PartialEq)]
+pub struct AnimationSet {
+    images: HashMap<(Pose, Direction), Image>,
+}
+impl AnimationSet {
+    // animations out of the same file share its frames
+    pub fn load(data: &AnimationData, assets: &mut Assets) -> Result<Self, Error> {
+        let mut images = HashMap::new();
+        for (pose, directions) in data {
+            for (direction, path) in directions {
+                images.insert((*pose, *direction), assets.load(path)?);
+            }
+        }
+        Ok(Self { images })
+    }
+    // the best match for the pose and direction and whether it has to be
+    // mirrored: the other side flipped, then idle the same way
+    pub fn resolve(&self, pose: Pose, direction: Direction) -> Option<(Pose, Direction, bool)> {
+        [pose, Pose::Idle].into_iter().find_map(|p| {
+            if self.images.contains_key(&(p, direction)) {
+                return Some((p, direction, false));
+            }
+            let m = direction.mirror()?;
+            self.images.contains_key(&(p, m)).then_some((p, m, true))
+        })
+    }
+    pub fn get(&self, pose: Pose, direction: Direction) -> Option<&Image> {
+        self.images.get(&(pose, direction))
+    }
+    pub fn get_mut(&mut self, pose: Pose, direction: Direction) -> Option<&mut Image> {
+        self.images.get_mut(&(pose, direction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{assets::Assets, geometry::vector};
+
+    use super::{AnimationSet, Direction, Pose};
+
+    #[test]
+    fn test_direction() {
+        assert_eq!(Direction::of(vector(3.0, 1.0)), Some(Direction::E));
+        assert_eq!(Direction::of(vector(-3.0, 3.0)), Some(Direction::W));
+        assert_eq!(Direction::of(vector(1.0, 3.0)), Some(Direction::S));
+        assert_eq!(Direction::of(vector(0.0, -1.0)), Some(Direction::N));
+        assert_eq!(Direction::of(vector(0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let data = HashMap::from([
+            (
+                Pose::Walk,
+                HashMap::from([(Direction::E, "resources/fox_walk.png".into())]),
+            ),
+            (
+                Pose::Idle,
+                HashMap::from([(Direction::S, "resources/fox.png".into())]),
+            ),
+        ]);
+        let set = AnimationSet::load(&data, &mut Assets::default()).unwrap();
+        use Direction::*;
+        assert_eq!(set.resolve(Pose::Walk, E), Some((Pose::Walk, E, false)));
+        assert_eq!(set.resolve(Pose::Walk, W), Some((Pose::Walk, E, true)));
+        assert_eq!(set.resolve(Pose::Walk, S), Some((Pose::Idle, S, false)));
+        assert_eq!(set.resolve(Pose::Talk, S), Some((Pose::Idle, S, false)));
+        assert_eq!(set.resolve(Pose::Walk, N), None);
+    }
+}
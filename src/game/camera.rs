@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use crate::geometry::{point, vector, Point, Rect, Vector};
+
+// how far the target can stray from the middle of the screen before the
+// camera moves, in each direction
+const DEAD_ZONE: Vector = Vector { x: 80.0, y: 60.0 };
+// the share of the way left to go the camera covers in a second is 1 - e^-EASING
+const EASING: f64 = 6.0;
+
+// which part of the room is on screen: follows a target once it leaves the
+// dead zone, easing after it, and never shows anything outside the room
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera {
+    // the top left of the screen in the room
+    position: Point,
+    viewport: Rect,
+    bounds: Rect,
+    pub dead_zone: Vector,
+    // 0 keeps up with the target without easing
+    pub easing: f64,
+}
+impl Camera {
+    pub fn new(viewport: Rect) -> Self {
+        Self {
+            position: point(0.0, 0.0),
+            viewport,
+            bounds: viewport,
+            dead_zone: DEAD_ZONE,
+            easing: EASING,
+        }
+    }
+    pub fn position(&self) -> Point {
+        self.position
+    }
+    pub fn set_viewport(&mut self, viewport: Rect) {
+        self.viewport = viewport;
+        self.position = self.clamp(self.position);
+    }
+    // the size of the room, the camera stays at the top left of rooms smaller
+    // than the screen
+    pub fn set_bounds(&mut self, bounds: Rect) {
+        self.bounds = bounds;
+        self.position = self.clamp(self.position);
+    }
+    // jumps straight to the target, e.g. when entering a room
+    pub fn center_on(&mut self, target: Point) {
+        let half = vector(self.viewport.w as f64, self.viewport.h as f64) * 0.5;
+        self.position = self.clamp(target - half);
+    }
+    pub fn follow(&mut self, target: Point, dt: Duration) {
+        let half = vector(self.viewport.w as f64, self.viewport.h as f64) * 0.5;
+        let offset = target - (self.position + half);
+        let beyond = |d: f64, zone: f64| {
+            if d > zone {
+                d - zone
+            } else if d < -zone {
+                d + zone
+            } else {
+                0.0
+            }
+        };
+        let goal = self.position
+            + vector(
+                beyond(offset.x, self.dead_zone.x),
+                beyond(offset.y, self.dead_zone.y),
+            );
+        let t = match self.easing {
+            e if e <= 0.0 => 1.0,
+            e => 1.0 - (-e * dt.as_secs_f64()).exp(),
+        };
+        self.position = self.clamp(self.position + (goal - self.position) * t);
+    }
+    pub fn to_screen(&self, p: Point) -> Point {
+        (p - self.position).into()
+    }
+    pub fn to_world(&self, p: Point) -> Point {
+        p + Vector::from(self.position)
+    }
+    fn clamp(&self, p: Point) -> Point {
+        let max_x = self.bounds.w.saturating_sub(self.viewport.w) as f64;
+        let max_y = self.bounds.h.saturating_sub(self.viewport.h) as f64;
+        point(p.x.clamp(0.0, max_x), p.y.clamp(0.0, max_y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::geometry::{point, rect};
+
+    use super::Camera;
+
+    #[test]
+    fn test_clamp() {
+        let mut camera = Camera::new(rect(100, 50));
+        camera.center_on(point(500.0, 500.0));
+        assert_eq!(camera.position(), point(0.0, 0.0));
+
+        camera.set_bounds(rect(300, 80));
+        camera.center_on(point(500.0, 500.0));
+        assert_eq!(camera.position(), point(200.0, 30.0));
+        camera.center_on(point(120.0, 10.0));
+        assert_eq!(camera.position(), point(70.0, 0.0));
+        assert_eq!(camera.to_screen(point(120.0, 10.0)), point(50.0, 10.0));
+        assert_eq!(camera.to_world(point(50.0, 10.0)), point(120.0, 10.0));
+    }
+
+    #[test]
+    fn test_follow() {
+        let mut camera = Camera::new(rect(100, 50));
+        camera.set_bounds(rect(1000, 50));
+        camera.easing = 0.0;
+        camera.dead_zone.x = 20.0;
+        let dt = Duration::from_millis(10);
+
+        // the target ends up on the edge of the dead zone, and can move
+        // within it without the camera following
+        camera.follow(point(120.0, 25.0), dt);
+        assert_eq!(camera.position(), point(50.0, 0.0));
+        camera.follow(point(80.0, 25.0), dt);
+        assert_eq!(camera.position(), point(50.0, 0.0));
+        camera.follow(point(20.0, 25.0), dt);
+        assert_eq!(camera.position(), point(0.0, 0.0));
+    }
+
+    #[test]
+    fn test_easing() {
+        let mut camera = Camera::new(rect(100, 50));
+        camera.set_bounds(rect(1000, 50));
+        camera.dead_zone.x = 0.0;
+        let dt = Duration::from_millis(100);
+        camera.follow(point(150.0, 25.0), dt);
+        let x = camera.position().x;
+        assert!(x > 0.0 && x < 100.0, "{}", x);
+        (0..50).for_each(|_| camera.follow(point(150.0, 25.0), dt));
+        assert!((camera.position().x - 100.0).abs() < 0.01);
+    }
+}
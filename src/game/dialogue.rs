@@ -0,0 +1,182 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+};
+
+use serde::Deserialize;
+
+use crate::{error::Error, scene};
+
+use super::Inventory;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum Condition {
+    Flag(String),
+    NotFlag(String),
+    Has(String),
+    Lacks(String),
+}
+impl Condition {
+    pub fn holds(&self, flags: &BTreeSet<String>, inventory: &Inventory) -> bool {
+        match self {
+            Self::Flag(f) => flags.contains(f),
+            Self::NotFlag(f) => !flags.contains(f),
+            Self::Has(i) => inventory.contains(i),
+            Self::Lacks(i) => !inventory.contains(i),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum Effect {
+    SetFlag(String),
+    GiveItem(String),
+    End,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Choice {
+    pub text: String,
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+    // the node to continue with, the conversation ends without one
+    #[serde(default)]
+    pub next: Option<String>,
+}
+impl Choice {
+    pub fn available(&self, flags: &BTreeSet<String>, inventory: &Inventory) -> bool {
+        self.conditions.iter().all(|c| c.holds(flags, inventory))
+    }
+    pub fn ends(&self) -> bool {
+        self.next.is_none() || self.effects.contains(&Effect::End)
+    }
+}
+
+// one line from the other side of the conversation and the player's replies
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Node {
+    pub text: String,
+    #[serde(default)]
+    pub choices: Vec<Choice>,
+}
+impl Node {
+    pub fn choices<'a>(
+        &'a self,
+        flags: &'a BTreeSet<String>,
+        inventory: &'a Inventory,
+    ) -> impl Iterator<Item = &'a Choice> {
+        self.choices
+            .iter()
+            .filter(move |c| c.available(flags, inventory))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Dialogue {
+    pub start: String,
+    pub nodes: HashMap<String, Node>,
+}
+impl Dialogue {
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let dialogue: Self = scene::load(path)?;
+        dialogue.validate(path)?;
+        Ok(dialogue)
+    }
+    fn validate(&self, path: &Path) -> Result<(), Error> {
+        let invalid = |reason: String| Err(Error::InvalidScene(path.to_owned(), reason));
+        if !self.nodes.contains_key(&self.start) {
+            return invalid(format!("unknown start node '{}'", self.start));
+        }
+        for (name, node) in &self.nodes {
+            let mut next = node.choices.iter().filter_map(|c| c.next.as_ref());
+            if let Some(next) = next.find(|n| !self.nodes.contains_key(*n)) {
+                return invalid(format!("node '{}' leads to unknown node '{}'", name, next));
+            }
+        }
+        Ok(())
+    }
+    pub fn node(&self, name: &str) -> Option<&Node> {
+        self.nodes.get(name)
+    }
+    // every item the dialogue can hand out, so they can be checked up front
+    pub fn items(&self) -> impl Iterator<Item = &str> {
+        self.nodes
+            .values()
+            .flat_map(|n| &n.choices)
+            .flat_map(|c| &c.effects)
+            .filter_map(|e| match e {
+                Effect::GiveItem(i) => Some(i.as_str()),
+                _ => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, path::Path};
+
+    use crate::{error::Error, game::Inventory, scene};
+
+    use super::{Condition, Dialogue, Effect};
+
+    #[test]
+    fn test_load_dialogue() {
+        let dialogue = Dialogue::load("resources/dialogue/fennec.ron").unwrap();
+        let start = dialogue.node(&dialogue.start).unwrap();
+        assert!(!start.choices.is_empty());
+        assert!(dialogue.items().all(|i| i == "bone"));
+    }
+
+    #[test]
+    fn test_invalid_dialogue() {
+        let s = r#"(
+            start: "hello",
+            nodes: {
+                "hello": (text: "Hi.", choices: [(text: "Bye.", next: Some("nowhere"))]),
+            },
+        )"#;
+        let dialogue: Dialogue = scene::parse(Path::new("test.ron"), s).unwrap();
+        let res = dialogue.validate(Path::new("test.ron"));
+        assert!(matches!(res, Err(Error::InvalidScene(_, _))));
+    }
+
+    #[test]
+    fn test_conditions() {
+        let s = r#"(
+            start: "hello",
+            nodes: {
+                "hello": (
+                    text: "Hi.",
+                    choices: [
+                        (text: "Again?", conditions: [Flag("met")], next: Some("hello")),
+                        (text: "Hello.", conditions: [NotFlag("met")], effects: [SetFlag("met")], next: Some("hello")),
+                        (text: "Ball?", conditions: [Has("ball")], effects: [End]),
+                    ],
+                ),
+            },
+        )"#;
+        let dialogue: Dialogue = scene::parse(Path::new("test.ron"), s).unwrap();
+        let node = dialogue.node("hello").unwrap();
+        let inventory = Inventory::default();
+        let mut flags = BTreeSet::new();
+        let texts = |flags: &BTreeSet<String>| {
+            node.choices(flags, &inventory)
+                .map(|c| c.text.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(texts(&flags), vec!["Hello."]);
+        flags.insert("met".to_owned());
+        assert_eq!(texts(&flags), vec!["Again?"]);
+
+        assert!(Condition::Lacks("ball".to_owned()).holds(&flags, &inventory));
+        assert!(node.choices[2].ends());
+        assert!(!node.choices[1].ends());
+        assert_eq!(
+            node.choices[1].effects,
+            vec![Effect::SetFlag("met".to_owned())]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use crate::{
+    buffer::Buffer,
+    geometry::{point, Point, Rect},
+    image::Image,
+};
+
+// where the first slot goes, from the left and up from the bottom of the screen
+const ORIGIN: Point = Point { x: 360.0, y: 28.0 };
+const SLOT_SIZE: f64 = 28.0;
+
+#[derive(Debug, PartialEq)]
+pub struct Item {
+    pub name: String,
+    pub description: String,
+    pub icon: Image,
+}
+
+// every item the player can carry, the ones they currently do, and which
+// pairs of them can be combined into something new
+#[derive(Default, Debug)]
+pub struct Inventory {
+    catalog: HashMap<String, Item>,
+    combinations: Vec<(String, String, String)>,
+    items: Vec<String>,
+}
+impl Inventory {
+    pub fn new(
+        catalog: HashMap<String, Item>,
+        combinations: Vec<(String, String, String)>,
+    ) -> Self {
+        Self {
+            catalog,
+            combinations,
+            items: vec![],
+        }
+    }
+    pub fn item(&self, name: &str) -> Option<&Item> {
+        self.catalog.get(name)
+    }
+    pub fn items(&self) -> impl Iterator<Item = &str> {
+        self.items.iter().map(|i| i.as_str())
+    }
+    pub fn contains(&self, name: &str) -> bool {
+        self.items.iter().any(|i| i == name)
+    }
+    pub fn add(&mut self, name: &str) -> bool {
+        if !self.catalog.contains_key(name) || self.contains(name) {
+            return false;
+        }
+        self.items.push(name.to_owned());
+        true
+    }
+    // replaces everything being carried, skipping anything not in the catalog
+    pub fn set_items(&mut self, items: &[String]) {
+        self.items.clear();
+        items.iter().for_each(|i| {
+            self.add(i);
+        });
+    }
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.items.len();
+        self.items.retain(|i| i != name);
+        self.items.len() != len
+    }
+    // replaces both items with the result of combining them, in either order
+    pub fn combine(&mut self, a: &str, b: &str) -> Option<String> {
+        if !self.contains(a) || !self.contains(b) {
+            return None;
+        }
+        let result = self
+            .combinations
+            .iter()
+            .find(|(x, y, _)| (x == a && y == b) || (x == b && y == a))
+            .map(|(_, _, r)| r.clone())?;
+        self.remove(a);
+        self.remove(b);
+        self.add(&result);
+        Some(result)
+    }
+    pub fn slot_at(&self, p: Point, screen: Rect) -> Option<&str> {
+        let origin = origin(screen);
+        if p.y < origin.y || p.y >= origin.y + SLOT_SIZE || p.x < origin.x {
+            return None;
+        }
+        let idx = ((p.x - origin.x) / SLOT_SIZE) as usize;
+        self.items.get(idx).map(|i| i.as_str())
+    }
+    pub fn draw(&self, buf: &mut Buffer) {
+        let origin = origin(buf.size());
+        for (i, name) in self.items.iter().enumerate() {
+            if let Some(item) = self.catalog.get(name) {
+                let p = point(origin.x + i as f64 * SLOT_SIZE, origin.y);
+                item.icon.draw(buf, p);
+            }
+        }
+    }
+}
+
+fn origin(screen: Rect) -> Point {
+    point(ORIGIN.x, screen.h as f64 - ORIGIN.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        game_state::SCREEN,
+        geometry::{point, rect},
+        image::Image,
+    };
+
+    use super::{Inventory, Item};
+
+    fn inventory() -> Inventory {
+        let catalog = ["ball", "stick", "ball_on_a_stick"]
+            .into_iter()
+            .map(|n| {
+                let item = Item {
+                    name: n.replace('_', " "),
+                    description: n.to_owned(),
+                    icon: Image::load(format!("resources/icons/{}.png", n)).unwrap(),
+                };
+                (n.to_owned(), item)
+            })
+            .collect::<HashMap<_, _>>();
+        let combinations = vec![(
+            "ball".to_owned(),
+            "stick".to_owned(),
+            "ball_on_a_stick".to_owned(),
+        )];
+        Inventory::new(catalog, combinations)
+    }
+
+    #[test]
+    fn test_add_remove() {
+        let mut inv = inventory();
+        assert!(inv.add("ball"));
+        assert!(!inv.add("ball"));
+        assert!(!inv.add("anvil"));
+        assert!(inv.contains("ball"));
+        assert_eq!(inv.slot_at(point(370.0, 440.0), SCREEN), Some("ball"));
+        assert_eq!(inv.slot_at(point(400.0, 440.0), SCREEN), None);
+        assert_eq!(
+            inv.slot_at(point(370.0, 230.0), rect(320, 240)),
+            Some("ball")
+        );
+        assert!(inv.remove("ball"));
+        assert!(!inv.contains("ball"));
+        inv.set_items(&["stick".to_owned(), "anvil".to_owned()]);
+        assert_eq!(inv.items().collect::<Vec<_>>(), vec!["stick"]);
+    }
+
+    #[test]
+    fn test_combine() {
+        let mut inv = inventory();
+        inv.add("ball");
+        assert_eq!(inv.combine("stick", "ball"), None);
+        inv.add("stick");
+        assert_eq!(
+            inv.combine("stick", "ball"),
+            Some("ball_on_a_stick".to_owned())
+        );
+        assert_eq!(inv.items().collect::<Vec<_>>(), vec!["ball_on_a_stick"]);
+    }
+}
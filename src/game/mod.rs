@@ -1,14 +1,26 @@
 mod actor;
+mod animation;
+mod camera;
+mod dialogue;
+mod inventory;
 mod object;
 mod pathfinding;
+mod room;
 mod scenery;
+mod verb;
 mod walkbox;
 
 pub use actor::Actor;
+pub use animation::{AnimationData, AnimationSet};
+pub use camera::Camera;
+pub use dialogue::{Choice, Dialogue, Effect, Node};
+pub use inventory::{Inventory, Item};
 pub use object::Object;
 pub use pathfinding::{astar, ShortestPath};
-pub use scenery::Scenery;
-pub use walkbox::WalkBox;
+pub use room::Rooms;
+pub use scenery::{Depth, Layer, Mask, Scenery};
+pub use verb::{Interaction, Response, Verb};
+pub use walkbox::{ScaleZone, WalkBox};
 
 use std::time::Duration;
 
@@ -19,7 +31,7 @@ use crate::{
 
 pub trait Updatable {
     fn mouse_over(&mut self, p: Point);
-    fn mouse_click(&mut self, p: Point);
+    fn mouse_click(&mut self, p: Point) -> bool;
     fn tick(&mut self, dt: Duration);
     fn draw(&self, buf: &mut Buffer);
 }
@@ -1,29 +1,74 @@
-use std::{path::Path, time::Duration};
+use std::time::Duration;
 
-use crate::{buffer::Buffer, geometry::Point, image::Image};
+use crate::{
+    buffer::Buffer,
+    geometry::{vector, Point},
+    image::Image,
+};
 
-use super::Updatable;
+use super::{Interaction, Updatable};
 
 #[derive(Debug, PartialEq)]
 pub struct Object {
     image: Image,
     location: Point,
+    interaction: Interaction,
+    z: Option<f64>,
+    hovered: bool,
 }
 impl Object {
-    pub fn new<T: AsRef<Path>>(path: T, loc: Point) -> Self {
-        let image = Image::load(path);
+    pub fn new(image: Image, loc: Point, interaction: Interaction, z: Option<f64>) -> Self {
         Self {
             image,
             location: loc,
+            interaction,
+            z,
+            hovered: false,
         }
     }
+    pub fn baseline(&self) -> f64 {
+        self.z
+            .unwrap_or_else(|| self.location.y + self.image.size().h as f64)
+    }
+    pub fn location(&self) -> Point {
+        self.location
+    }
+    pub fn set_location(&mut self, p: Point) {
+        self.location = p;
+    }
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+    pub fn image_mut(&mut self) -> &mut Image {
+        &mut self.image
+    }
+    pub fn interaction(&self) -> &Interaction {
+        &self.interaction
+    }
+    pub fn is_hovered(&self) -> bool {
+        self.hovered
+    }
+    // where the character stands to use the object, below its middle by default
+    pub fn interaction_point(&self) -> Point {
+        let s = self.image.size();
+        self.interaction
+            .walk_to
+            .unwrap_or_else(|| self.location + vector(s.w as f64 / 2.0, s.h as f64))
+    }
+    fn contains(&self, p: Point) -> bool {
+        let s = self.image.size();
+        p.x >= self.location.x
+            && p.y >= self.location.y
+            && p.x < self.location.x + s.w as f64
+            && p.y < self.location.y + s.h as f64
+    }
 }
 impl Updatable for Object {
-    fn mouse_over(&mut self, _p: Point) {
-        //
+    fn mouse_over(&mut self, p: Point) {
+        self.hovered = self.contains(p);
     }
-    fn mouse_click(&mut self, _p: Point) {
-        //
+    fn mouse_click(&mut self, p: Point) -> bool {
+        self.contains(p)
     }
     fn tick(&mut self, dt: Duration) {
         if let Image::Animated(img) = &mut self.image {
@@ -61,7 +61,7 @@ pub fn astar(graph: &Graph, start: Point, goal: Point) -> Option<ShortestPath> {
 
     while let Some(State {
         position: current_position,
-        cost: current_cost,
+        ..
     }) = frontier.pop()
     {
         if current_position == goal.into() {
@@ -69,11 +69,12 @@ pub fn astar(graph: &Graph, start: Point, goal: Point) -> Option<ShortestPath> {
         }
         for edge in graph.neighbours(current_position.into()) {
             let next_position: UPoint = edge.end.into();
-            let next_cost = edge.length() + current_cost;
+            // the cost so far, the queue's is the estimate through here
+            let next_cost = edge.length() + distances[&current_position];
 
             if next_cost < distances[&next_position] {
                 distances.insert(next_position, next_cost);
-                let priority = next_cost + (goal - Point::from(current_position)).length();
+                let priority = next_cost + (goal - Point::from(next_position)).length();
                 frontier.push(State {
                     position: next_position,
                     cost: priority,
@@ -100,6 +101,14 @@ impl ShortestPath {
             points: assemble_path(paths, start, goal),
         }
     }
+    // a path found some other way, from its first point to its last
+    pub fn through(points: Vec<Point>) -> Self {
+        Self {
+            start: points.first().copied().unwrap_or_default(),
+            end: points.last().copied().unwrap_or_default(),
+            points,
+        }
+    }
     pub fn points(&self) -> impl Iterator<Item = &Point> {
         self.points.iter()
     }
@@ -164,5 +173,16 @@ mod tests {
         graph.add_temporary_edges(start, end);
         let path = astar(&graph, start, end);
         assert!(path.is_some());
+
+        // the shortest way round the notch, not a detour past the far corner
+        let start = point(500.0, 100.0);
+        let end = point(90.0, 210.0);
+        graph.add_temporary_edges(start, end);
+        let path = astar(&graph, start, end).unwrap();
+        let points: Vec<_> = path.points().copied().collect();
+        assert_eq!(
+            points,
+            vec![start, point(360.0, 240.0), point(300.0, 240.0), end]
+        );
     }
 }
@@ -0,0 +1,783 @@
+use std::{
+    collections::{HashMap, HashSet},
+    iter, mem,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    assets::Assets,
+    error::Error,
+    geometry::{Graph, LineSegment, NavMesh, Point, Polygon},
+    image::Image,
+    save::{FrameState, ObjectState, RoomState},
+    scene::{self, Pathfinding, RoomData},
+    script::Program,
+};
+
+use super::{
+    Actor, AnimationSet, Interaction, Layer, Mask, Object, Response, ScaleZone, Scenery,
+    ShortestPath, WalkBox,
+};
+
+#[derive(Debug)]
+pub struct Exit {
+    pub area: Polygon,
+    pub room: String,
+    pub entry: Option<String>,
+}
+
+// finds paths through the room the way its data asks for
+#[derive(Debug)]
+pub enum Navigation {
+    Graph(Graph),
+    Mesh(NavMesh),
+}
+
+#[derive(Debug)]
+pub struct Room {
+    pub path: PathBuf,
+    pub spawn: Point,
+    pub entries: HashMap<String, Point>,
+    pub exits: Vec<Exit>,
+    pub scenery: Scenery,
+    pub masks: Vec<Mask>,
+    pub walkbox: WalkBox,
+    pub navigation: Navigation,
+    pub actors: Vec<Actor>,
+    pub objects: Vec<Object>,
+    pub on_enter: Option<Program>,
+    // every script the room's responses can run, parsed up front
+    pub scripts: HashMap<PathBuf, Program>,
+    // what the room was built from, its images are loaded from it again
+    // when the player comes back
+    data: RoomData,
+}
+impl Room {
+    pub fn load<T: AsRef<Path>>(path: T, assets: &mut Assets) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = scene::load(path)?;
+        Self::from_data(path, data, assets)
+    }
+    fn from_data(path: &Path, data: RoomData, assets: &mut Assets) -> Result<Self, Error> {
+        // saves and scripts tell objects and actors apart by name
+        let objects = data.objects.iter().map(|o| o.name.as_str());
+        let actors = data.actors.iter().filter_map(|a| a.name.as_deref());
+        for (kind, names) in [
+            ("objects", duplicate(objects)),
+            ("actors", duplicate(actors)),
+        ] {
+            if let Some(name) = names {
+                return Err(Error::InvalidScene(
+                    path.to_owned(),
+                    format!("two {} named '{}'", kind, name),
+                ));
+            }
+        }
+        let exterior = scene::polygon(path, &data.walkbox.exterior)?;
+        let interior = data
+            .walkbox
+            .interior
+            .iter()
+            .map(|p| scene::polygon(path, p))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut walkbox = WalkBox::new(exterior, interior);
+        for a in &data.walkbox.areas {
+            let interior = a
+                .interior
+                .iter()
+                .map(|p| scene::polygon(path, p))
+                .collect::<Result<Vec<_>, _>>()?;
+            let exterior = scene::polygon(path, &a.exterior)?;
+            walkbox.add_area(&a.name, exterior, interior, a.enabled);
+        }
+        walkbox.scale = data.walkbox.scale.as_ref().map(|s| ScaleZone {
+            far: s.far,
+            near: s.near,
+        });
+        let navigation = match data.walkbox.pathfinding {
+            Pathfinding::Graph => Navigation::Graph(Graph::new(walkbox.clone())),
+            Pathfinding::NavMesh => Navigation::Mesh(
+                NavMesh::new(&walkbox).map_err(|e| Error::InvalidScene(path.to_owned(), e))?,
+            ),
+        };
+
+        let exits = data
+            .exits
+            .iter()
+            .map(|e| {
+                Ok(Exit {
+                    area: scene::polygon(path, &e.area)?,
+                    room: e.room.clone(),
+                    entry: e.entry.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let entries = data
+            .entries
+            .iter()
+            .map(|(name, c)| (name.clone(), Point::from(*c)))
+            .collect();
+
+        let mut scenery = Scenery::new(assets.image(scene::asset(&data.background)?));
+        for l in &data.layers {
+            let image = assets.image(scene::asset(&l.image)?);
+            let scroll = l.scroll.unwrap_or(1.0);
+            scenery
+                .layers
+                .push(Layer::new(image, l.position.into(), scroll, l.depth));
+        }
+        let actors = data
+            .actors
+            .iter()
+            .map(|a| {
+                let image = assets.image(scene::asset(&a.image)?);
+                let mut actor = Actor::new(image, a.location.into(), a.speed);
+                actor.z = a.z;
+                actor.animations = AnimationSet::load(&a.animations, assets)?;
+                actor.interaction = a.name.as_ref().map(|name| {
+                    Interaction::new(
+                        name,
+                        a.walk_to.map(Point::from),
+                        a.responses.clone(),
+                        a.uses.clone(),
+                    )
+                });
+                Ok(actor)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let objects = data
+            .objects
+            .iter()
+            .map(|o| {
+                let image = assets.image(scene::asset(&o.image)?);
+                let interaction = Interaction::new(
+                    &o.name,
+                    o.walk_to.map(Point::from),
+                    o.responses.clone(),
+                    o.uses.clone(),
+                );
+                Ok(Object::new(image, o.location.into(), interaction, o.z))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let masks = data
+            .masks
+            .iter()
+            .map(|m| {
+                let area = scene::polygon(path, &m.area)?;
+                let bottom = area.vertices.iter().map(|v| v.y).fold(f64::MIN, f64::max);
+                Ok(scenery.mask(&area, m.baseline.unwrap_or(bottom)))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let on_enter = data.script.as_ref().map(Program::load).transpose()?;
+        let mut scripts = HashMap::new();
+        let interactions = actors
+            .iter()
+            .filter_map(|a| a.interaction.as_ref())
+            .chain(objects.iter().map(|o| o.interaction()));
+        for i in interactions {
+            for response in i.responses.values().chain(i.uses.values()) {
+                if let Response::Run(script) = response {
+                    if !scripts.contains_key(script) {
+                        scripts.insert(script.clone(), Program::load(script)?);
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            path: path.to_owned(),
+            spawn: data.spawn.into(),
+            entries,
+            exits,
+            scenery,
+            masks,
+            walkbox,
+            navigation,
+            actors,
+            objects,
+            on_enter,
+            scripts,
+            data,
+        })
+    }
+    // lets go of every image so the cache can drop the ones no other room
+    // uses, the rest of the room stays as it is
+    pub fn unload(&mut self) {
+        for image in self.scenery.images_mut() {
+            *image = Image::default();
+        }
+        for a in &mut self.actors {
+            a.image = Image::default();
+            a.animations = AnimationSet::default();
+        }
+        for o in &mut self.objects {
+            *o.image_mut() = Image::default();
+        }
+    }
+    // gets the images back after `unload`, from the cache where they are
+    // still in it; animations start over unless restored afterwards
+    pub fn load_images(&mut self, assets: &mut Assets) -> Result<(), Error> {
+        let data = &self.data;
+        let paths = iter::once(&data.background).chain(data.layers.iter().map(|l| &l.image));
+        for (image, path) in self.scenery.images_mut().zip(paths) {
+            *image = assets.image(path);
+        }
+        for (a, d) in self.actors.iter_mut().zip(&data.actors) {
+            a.image = assets.image(&d.image);
+            a.animations = AnimationSet::load(&d.animations, assets)?;
+        }
+        for o in &mut self.objects {
+            let name = &o.interaction().name;
+            if let Some(d) = data.objects.iter().find(|d| &d.name == name) {
+                *o.image_mut() = assets.image(&d.image);
+            }
+        }
+        Ok(())
+    }
+    pub fn entry(&self, name: Option<&str>) -> Result<Point, Error> {
+        match name {
+            None => Ok(self.spawn),
+            Some(n) => self.entries.get(n).copied().ok_or_else(|| {
+                Error::InvalidScene(self.path.clone(), format!("no entry named '{}'", n))
+            }),
+        }
+    }
+    pub fn actor_named(&self, name: &str) -> Option<usize> {
+        self.actors
+            .iter()
+            .position(|a| a.interaction.as_ref().is_some_and(|i| i.name == name))
+    }
+    pub fn object_named(&self, name: &str) -> Option<usize> {
+        self.objects
+            .iter()
+            .position(|o| o.interaction().name == name)
+    }
+    pub fn exit_at(&self, p: Point) -> Option<&Exit> {
+        self.exits.iter().find(|e| e.area.contains(p))
+    }
+    pub fn state(&self) -> RoomState {
+        let objects = self
+            .objects
+            .iter()
+            .map(|o| ObjectState {
+                name: o.interaction().name.clone(),
+                location: (o.location().x, o.location().y),
+                frame: FrameState::of(o.image()),
+            })
+            .collect();
+        let actors = self.actors.iter().map(|a| a.state()).collect();
+        let areas = self
+            .walkbox
+            .enabled()
+            .map(|(name, on)| (name.to_owned(), on))
+            .collect();
+        RoomState {
+            objects,
+            actors,
+            areas,
+        }
+    }
+    pub fn restore(&mut self, state: &RoomState) {
+        self.objects.retain(|o| {
+            let name = &o.interaction().name;
+            state.objects.iter().any(|s| &s.name == name)
+        });
+        self.apply(state);
+    }
+    // puts what's still in the room where the state has it, leaving
+    // anything the state doesn't know about as the room file has it
+    fn apply(&mut self, state: &RoomState) {
+        for o in self.objects.iter_mut() {
+            if let Some(s) = state
+                .objects
+                .iter()
+                .find(|s| s.name == o.interaction().name)
+            {
+                o.set_location(s.location.into());
+                FrameState::apply(s.frame, o.image_mut());
+            }
+        }
+        for a in self.actors.iter_mut() {
+            let name = a.interaction.as_ref().map(|i| &i.name);
+            if let Some(s) = state
+                .actors
+                .iter()
+                .find(|s| name.is_some() && s.name.as_ref() == name)
+            {
+                a.restore(s);
+            }
+        }
+        for (name, enabled) in &state.areas {
+            self.set_walk_area(name, *enabled);
+        }
+    }
+    // the objects the room was read with that have gone since
+    fn picked_up(&self) -> HashSet<&str> {
+        self.data
+            .objects
+            .iter()
+            .map(|o| o.name.as_str())
+            .filter(|n| self.object_named(n).is_none())
+            .collect()
+    }
+    // switches a walk area and keeps the pathfinding in step, false if the
+    // room has no area by that name
+    pub fn set_walk_area(&mut self, name: &str, enabled: bool) -> bool {
+        if self.walkbox.enabled().any(|a| a == (name, enabled)) {
+            return true;
+        }
+        if self.walkbox.set_enabled(name, enabled).is_none() {
+            return false;
+        }
+        match &mut self.navigation {
+            Navigation::Graph(graph) => graph.set_enabled(name, enabled),
+            Navigation::Mesh(mesh) => mesh.set_enabled(name, enabled),
+        }
+    }
+    pub fn path(&mut self, from: Point, to: Point) -> Option<ShortestPath> {
+        match &mut self.navigation {
+            Navigation::Graph(graph) => {
+                graph.add_temporary_edges(from, to);
+                graph.path_to(from, to)
+            }
+            Navigation::Mesh(mesh) => mesh.path_to(from, to),
+        }
+    }
+    // what pathfinding works with, for drawing while debugging
+    pub fn navigation_lines(&self) -> Vec<LineSegment> {
+        match &self.navigation {
+            Navigation::Graph(graph) => graph.walkable_edges().copied().collect(),
+            Navigation::Mesh(mesh) => mesh.edges().collect(),
+        }
+    }
+}
+
+// all rooms known to the game: only the one the player is in has its images,
+// the ones they've left keep everything else and get their images back on
+// the way in, so their assets can go in the meantime
+#[derive(Debug)]
+pub struct Rooms {
+    paths: HashMap<String, PathBuf>,
+    current: String,
+    room: Room,
+    // the rooms left behind, without their images
+    unloaded: HashMap<String, Room>,
+    // what every room other than the current one looked like when it was
+    // left, or in the save game that was loaded
+    left: HashMap<String, RoomState>,
+}
+impl Rooms {
+    pub fn new(
+        paths: HashMap<String, PathBuf>,
+        start: &str,
+        assets: &mut Assets,
+    ) -> Result<Self, Error> {
+        let room = Self::read(&paths, start, assets)?;
+        Ok(Self {
+            paths,
+            current: start.to_owned(),
+            room,
+            unloaded: HashMap::new(),
+            left: HashMap::new(),
+        })
+    }
+    fn read(
+        paths: &HashMap<String, PathBuf>,
+        name: &str,
+        assets: &mut Assets,
+    ) -> Result<Room, Error> {
+        let path = paths
+            .get(name)
+            .ok_or_else(|| Error::InvalidScene(PathBuf::from(name), "unknown room".to_owned()))?;
+        let room = Room::load(path, assets)?;
+        if let Some(e) = room.exits.iter().find(|e| !paths.contains_key(&e.room)) {
+            return Err(Error::InvalidScene(
+                path.clone(),
+                format!("exit leads to unknown room '{}'", e.room),
+            ));
+        }
+        Ok(room)
+    }
+    pub fn current(&self) -> &Room {
+        &self.room
+    }
+    pub fn current_mut(&mut self) -> &mut Room {
+        &mut self.room
+    }
+    pub fn current_name(&self) -> &str {
+        &self.current
+    }
+    // every room visited so far
+    pub fn states(&self) -> HashMap<String, RoomState> {
+        let mut states = self.left.clone();
+        states.insert(self.current.clone(), self.room.state());
+        states
+    }
+    // reloads the current room from disk and puts it and the others back the
+    // way they were; on error the rooms are left untouched
+    pub fn restore(
+        &mut self,
+        current: &str,
+        states: &HashMap<String, RoomState>,
+        assets: &mut Assets,
+    ) -> Result<(), Error> {
+        if let Some(name) = states.keys().find(|n| !self.paths.contains_key(*n)) {
+            return Err(Error::InvalidScene(
+                PathBuf::from(name),
+                "unknown room".to_owned(),
+            ));
+        }
+        let mut room = Self::read(&self.paths, current, assets)?;
+        if let Some(state) = states.get(current) {
+            room.restore(state);
+        }
+        self.room = room;
+        self.current = current.to_owned();
+        self.left = states.clone();
+        self.left.remove(current);
+        // they may have lost objects the save still has
+        self.unloaded.clear();
+        assets.collect();
+        Ok(())
+    }
+    // reads the current room from disk again, keeping its state; the others
+    // are read again when they're entered
+    pub fn reload(&mut self, assets: &mut Assets) -> Result<(), Error> {
+        let mut room = Self::read(&self.paths, &self.current, assets)?;
+        // objects new to the file stay, only the ones picked up go
+        let picked_up = self.room.picked_up();
+        room.objects
+            .retain(|o| !picked_up.contains(o.interaction().name.as_str()));
+        room.apply(&self.room.state());
+        self.room = room;
+        self.unloaded.clear();
+        Ok(())
+    }
+    // switches to the named room and returns where the character should
+    // appear, unloading whatever the room left behind doesn't share with it
+    pub fn enter(
+        &mut self,
+        name: &str,
+        entry: Option<&str>,
+        assets: &mut Assets,
+    ) -> Result<Point, Error> {
+        if name == self.current {
+            return self.room.entry(entry);
+        }
+        let mut room = match self.unloaded.remove(name) {
+            Some(room) => room,
+            None => Self::read(&self.paths, name, assets)?,
+        };
+        let spawn = match room.load_images(assets).and_then(|_| room.entry(entry)) {
+            Ok(spawn) => spawn,
+            Err(e) => {
+                room.unload();
+                self.unloaded.insert(name.to_owned(), room);
+                return Err(e);
+            }
+        };
+        if let Some(state) = self.left.remove(name) {
+            room.restore(&state);
+        }
+        let mut previous = mem::replace(&mut self.room, room);
+        let previous_name = mem::replace(&mut self.current, name.to_owned());
+        self.left.insert(previous_name.clone(), previous.state());
+        previous.unload();
+        self.unloaded.insert(previous_name, previous);
+        assets.collect();
+        Ok(spawn)
+    }
+}
+
+fn duplicate<'a>(mut names: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let mut seen = HashSet::new();
+    names.find(|n| !seen.insert(*n))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, env, fs, path::Path};
+
+    use crate::{
+        assets::Assets,
+        error::Error,
+        geometry::{point, rect},
+        scene,
+    };
+
+    use super::{Navigation, Room, Rooms};
+
+    fn rooms(assets: &mut Assets) -> Rooms {
+        let paths = HashMap::from([
+            ("start".to_owned(), "resources/rooms/start.ron".into()),
+            ("hall".to_owned(), "resources/rooms/hall.ron".into()),
+        ]);
+        Rooms::new(paths, "start", assets).unwrap()
+    }
+
+    #[test]
+    fn test_load_room() {
+        let room = Room::load("resources/rooms/start.ron", &mut Assets::default()).unwrap();
+        assert_eq!(room.spawn, point(150.0, 150.0));
+        assert_eq!(room.walkbox.edges().count(), 13);
+        assert!(matches!(room.navigation, Navigation::Graph(_)));
+        assert!(!room.navigation_lines().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_room() {
+        let path = Path::new("test.ron");
+        let mut assets = Assets::default();
+        let data = scene::parse(
+            path,
+            r#"(
+                background: "resources/Pixel_Art_Background.png",
+                spawn: (0.0, 0.0),
+                walkbox: (exterior: [(0.0, 0.0), (10.0, 0.0)]),
+            )"#,
+        )
+        .unwrap();
+        let room = Room::from_data(path, data, &mut assets);
+        assert!(matches!(room, Err(Error::InvalidScene(_, _))));
+
+        // an obstacle sticking out of an area the navmesh can't cut up, even
+        // one that's switched off
+        let data = scene::parse(
+            path,
+            r#"(
+                background: "resources/Pixel_Art_Background.png",
+                spawn: (5.0, 5.0),
+                walkbox: (
+                    exterior: [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)],
+                    areas: [(
+                        name: "ledge",
+                        exterior: [(10.0, 0.0), (20.0, 0.0), (20.0, 10.0), (10.0, 10.0)],
+                        interior: [[(15.0, 2.0), (25.0, 2.0), (25.0, 8.0), (15.0, 8.0)]],
+                        enabled: false,
+                    )],
+                    pathfinding: NavMesh,
+                ),
+            )"#,
+        )
+        .unwrap();
+        let room = Room::from_data(path, data, &mut assets);
+        assert!(matches!(room, Err(Error::InvalidScene(_, _))));
+
+        let data = scene::parse(
+            path,
+            r#"(
+                background: "resources/Pixel_Art_Background.png",
+                spawn: (0.0, 0.0),
+                walkbox: (exterior: [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]),
+                objects: [
+                    (name: "rock", image: "resources/ball.png", location: (1.0, 1.0)),
+                    (name: "rock", image: "resources/ball.png", location: (5.0, 5.0)),
+                ],
+            )"#,
+        )
+        .unwrap();
+        let room = Room::from_data(path, data, &mut assets);
+        assert!(matches!(room, Err(Error::InvalidScene(_, _))));
+
+        let data = scene::parse(
+            path,
+            r#"(
+                background: "resources/missing.png",
+                spawn: (0.0, 0.0),
+                walkbox: (exterior: [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]),
+            )"#,
+        )
+        .unwrap();
+        let room = Room::from_data(path, data, &mut assets);
+        assert!(matches!(room, Err(Error::Io(_, _))));
+    }
+
+    #[test]
+    fn test_walk_areas() {
+        let path = Path::new("test.ron");
+        let data = scene::parse(
+            path,
+            r#"(
+                background: "resources/Pixel_Art_Background.png",
+                spawn: (50.0, 50.0),
+                walkbox: (
+                    exterior: [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)],
+                    areas: [(
+                        name: "bridge",
+                        exterior: [(100.0, 40.0), (200.0, 40.0), (200.0, 60.0), (100.0, 60.0)],
+                        enabled: false,
+                    )],
+                ),
+            )"#,
+        )
+        .unwrap();
+        let mut room = Room::from_data(path, data, &mut Assets::default()).unwrap();
+        let across = point(150.0, 50.0);
+        assert!(!room.walkbox.walkable(across));
+        assert!(room.set_walk_area("bridge", true));
+        assert!(room.walkbox.walkable(across));
+        assert!(!room.set_walk_area("gate", true));
+
+        let state = room.state();
+        assert_eq!(state.areas.get("bridge"), Some(&true));
+        room.set_walk_area("bridge", false);
+        room.restore(&state);
+        assert!(room.walkbox.walkable(across));
+        assert!(room.path(room.spawn, across).is_some());
+    }
+
+    #[test]
+    fn test_navmesh_room() {
+        let path = Path::new("test.ron");
+        let data = scene::parse(
+            path,
+            r#"(
+                background: "resources/Pixel_Art_Background.png",
+                spawn: (50.0, 50.0),
+                walkbox: (
+                    exterior: [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)],
+                    areas: [(
+                        name: "bridge",
+                        exterior: [(100.0, 40.0), (200.0, 40.0), (200.0, 60.0), (100.0, 60.0)],
+                        enabled: false,
+                    )],
+                    pathfinding: NavMesh,
+                ),
+            )"#,
+        )
+        .unwrap();
+        let mut room = Room::from_data(path, data, &mut Assets::default()).unwrap();
+        assert!(matches!(room.navigation, Navigation::Mesh(_)));
+        let across = point(150.0, 50.0);
+        assert!(room.path(room.spawn, across).is_none());
+        room.set_walk_area("bridge", true);
+        let route = room.path(point(10.0, 10.0), across).unwrap();
+        let points: Vec<_> = route.points().copied().collect();
+        assert_eq!(points, vec![point(10.0, 10.0), point(100.0, 40.0), across]);
+        assert!(!room.navigation_lines().is_empty());
+    }
+
+    #[test]
+    fn test_room_transition() {
+        let mut assets = Assets::default();
+        let mut rooms = rooms(&mut assets);
+        assert_eq!(rooms.current, "start");
+
+        let exit = rooms.current().exit_at(point(70.0, 420.0)).unwrap();
+        let (room, entry) = (exit.room.clone(), exit.entry.clone());
+        let spawn = rooms.enter(&room, entry.as_deref(), &mut assets).unwrap();
+        assert_eq!(rooms.current, "hall");
+        assert!(rooms.current().walkbox.contains(spawn));
+        assert!(rooms.current().exit_at(spawn).is_none());
+
+        assert!(rooms.enter("start", Some("nowhere"), &mut assets).is_err());
+        assert_eq!(rooms.current, "hall");
+        assert!(rooms.enter("cellar", None, &mut assets).is_err());
+    }
+
+    #[test]
+    fn test_unload() {
+        let mut assets = Assets::default();
+        let mut rooms = rooms(&mut assets);
+        let ball = assets.load("resources/ball.png").unwrap();
+        assert_eq!(ball.handles(), 3);
+
+        // once the room is gone only the cache and the copy here are left
+        rooms.enter("hall", None, &mut assets).unwrap();
+        assert_eq!(ball.handles(), 2);
+
+        // coming back gets the images again for the room as it was left,
+        // without reading it again
+        rooms.current_mut().objects.clear();
+        rooms.current_mut().spawn = point(1.0, 2.0);
+        rooms.enter("start", None, &mut assets).unwrap();
+        assert_eq!(ball.handles(), 3);
+        assert_eq!(rooms.unloaded["hall"].scenery.size(), rect(0, 0));
+        rooms.enter("hall", None, &mut assets).unwrap();
+        assert!(rooms.current().objects.is_empty());
+        assert_eq!(rooms.current().spawn, point(1.0, 2.0));
+        assert_ne!(rooms.current().scenery.size(), rect(0, 0));
+    }
+
+    #[test]
+    fn test_actor_state() {
+        let path = Path::new("test.ron");
+        let mut assets = Assets::default();
+        let mut room = |actors: &str| {
+            let data = scene::parse(
+                path,
+                &format!(
+                    r#"(
+                        background: "resources/Pixel_Art_Background.png",
+                        spawn: (0.0, 0.0),
+                        walkbox: (exterior: [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0)]),
+                        actors: [{}],
+                    )"#,
+                    actors
+                ),
+            )
+            .unwrap();
+            Room::from_data(path, data, &mut assets).unwrap()
+        };
+        let fox = r#"(name: Some("fox"), image: "resources/fox.png", location: (10.0, 10.0))"#;
+        let owl = r#"(name: Some("owl"), image: "resources/fox.png", location: (20.0, 20.0))"#;
+        let mut before = room(&format!("{}, {}", fox, owl));
+        before.actors[0].location = point(50.0, 50.0);
+        let state = before.state();
+
+        // swapped round in the room file, each still gets its own state
+        let mut after = room(&format!("{}, {}", owl, fox));
+        after.restore(&state);
+        assert_eq!(after.actors[0].location, point(20.0, 20.0));
+        assert_eq!(after.actors[1].location, point(50.0, 50.0));
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut assets = Assets::default();
+        let mut rooms = rooms(&mut assets);
+        rooms.current_mut().objects.clear();
+        let states = rooms.states();
+
+        let mut restored = self::rooms(&mut assets);
+        restored.enter("hall", None, &mut assets).unwrap();
+        restored.restore("start", &states, &mut assets).unwrap();
+        assert_eq!(restored.current, "start");
+        assert!(restored.current().objects.is_empty());
+        assert!(!restored.left.contains_key("hall"));
+        assert!(restored.restore("cellar", &states, &mut assets).is_err());
+        assert_eq!(restored.current, "start");
+    }
+
+    #[test]
+    fn test_reload() {
+        let dir = env::temp_dir().join("crust_engine_test_reload");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("start.ron");
+        let start = fs::read_to_string("resources/rooms/start.ron").unwrap();
+        fs::write(&path, &start).unwrap();
+        let mut assets = Assets::default();
+        let paths = HashMap::from([
+            ("start".to_owned(), path.clone()),
+            ("hall".to_owned(), "resources/rooms/hall.ron".into()),
+        ]);
+        let mut rooms = Rooms::new(paths, "start", &mut assets).unwrap();
+        rooms.current_mut().objects.clear();
+
+        // the ball stays picked up, the stick added meanwhile shows up
+        let stick = r#"objects: [
+            (name: "stick", image: "resources/stick.png", location: (100.0, 100.0)),"#;
+        fs::write(&path, start.replace("objects: [", stick)).unwrap();
+        rooms.reload(&mut assets).unwrap();
+        let names: Vec<_> = rooms
+            .current()
+            .objects
+            .iter()
+            .map(|o| o.interaction().name.as_str())
+            .collect();
+        assert_eq!(names, ["stick"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
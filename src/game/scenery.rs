@@ -1,18 +1,258 @@
-use crate::{buffer::Buffer, image::Image};
+use std::{iter, time::Duration};
+
+use serde::Deserialize;
+
+use crate::{
+    buffer::Buffer,
+    geometry::{point, rect, Point, Polygon, Rect, Vector},
+    image::{Bitmap, Image},
+};
+
+// where a layer is drawn, layers at the same depth keep the order they are
+// listed in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum Depth {
+    // behind the background, showing through where it's transparent
+    Behind,
+    // over the background, behind everyone
+    #[default]
+    Back,
+    // sorted in with actors and objects, like a mask
+    Baseline(f64),
+    // over everyone
+    Front,
+}
+
+// an image that moves `scroll` times as far as the camera: less than 1 for
+// things in the distance, more than 1 for things close up
+#[derive(Debug, PartialEq)]
+pub struct Layer {
+    image: Image,
+    // where it is with the camera at the top left of the room
+    position: Point,
+    scroll: f64,
+    pub depth: Depth,
+}
+impl Layer {
+    pub fn new(image: Image, position: Point, scroll: f64, depth: Depth) -> Self {
+        Self {
+            image,
+            position,
+            scroll,
+            depth,
+        }
+    }
+    pub fn draw(&self, buffer: &mut Buffer) {
+        let camera = Vector::from(buffer.camera());
+        self.image
+            .draw(buffer, self.position + camera * (1.0 - self.scroll));
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub struct Scenery {
     image: Image,
+    pub layers: Vec<Layer>,
 }
 impl Scenery {
-    pub fn new() -> Self {
-        let image = Image::load("resources/Pixel_Art_Background.png");
-        Self { image }
+    pub fn new(image: Image) -> Self {
+        Self {
+            image,
+            layers: vec![],
+        }
+    }
+    // the background with the layers behind and on it
+    pub fn draw(&self, buffer: &mut Buffer) {
+        let behind = self.layers_at(Depth::Behind);
+        match &self.image {
+            Image::Static(i) if behind.clone().next().is_none() => buffer.draw_opaque(i.data()),
+            // an animated background is drawn like any other image, its
+            // frames needn't cover the whole room
+            _ => {
+                buffer.clear();
+                behind.for_each(|l| l.draw(buffer));
+                self.image.draw(buffer, point(0.0, 0.0));
+            }
+        }
+        self.layers_at(Depth::Back).for_each(|l| l.draw(buffer));
+    }
+    pub fn draw_front(&self, buffer: &mut Buffer) {
+        self.layers_at(Depth::Front).for_each(|l| l.draw(buffer));
+    }
+    fn layers_at(&self, depth: Depth) -> impl Iterator<Item = &Layer> + Clone {
+        self.layers.iter().filter(move |l| l.depth == depth)
+    }
+    pub fn tick(&mut self, dt: Duration) {
+        for image in self.images_mut() {
+            if let Some(a) = image.animation_mut() {
+                a.update(dt);
+            }
+        }
+    }
+    // the background and then each layer's image
+    pub fn images_mut(&mut self) -> impl Iterator<Item = &mut Image> {
+        iter::once(&mut self.image).chain(self.layers.iter_mut().map(|l| &mut l.image))
+    }
+    // how big the room is
+    pub fn size(&self) -> Rect {
+        self.image.size()
+    }
+    // cuts the area out of the background, leaving everything around it
+    // transparent
+    pub fn mask(&self, area: &Polygon, baseline: f64) -> Mask {
+        let background = match &self.image {
+            Image::Static(i) => i.data(),
+            Image::Animated(_) => return Mask::new(Bitmap::default(), point(0.0, 0.0), baseline),
+        };
+        let (w, h) = (background.cols(), background.rows());
+        let xs = area.vertices.iter().map(|v| v.x);
+        let ys = area.vertices.iter().map(|v| v.y);
+        let clamp = |v: f64, max: usize| v.max(0.0).min(max as f64) as usize;
+        let (left, right) = (
+            clamp(xs.clone().fold(f64::MAX, f64::min).floor(), w),
+            clamp(xs.fold(f64::MIN, f64::max).ceil(), w),
+        );
+        let (top, bottom) = (
+            clamp(ys.clone().fold(f64::MAX, f64::min).floor(), h),
+            clamp(ys.fold(f64::MIN, f64::max).ceil(), h),
+        );
+
+        let mut data = vec![0; (right - left) * (bottom - top) * 4];
+        let mut i = 0;
+        for y in top..bottom {
+            for x in left..right {
+                if area.contains(point(x as f64 + 0.5, y as f64 + 0.5)) {
+                    let src = (y * w + x) * 4;
+                    data[i..i + 4].copy_from_slice(&background.data()[src..src + 4]);
+                }
+                i += 4;
+            }
+        }
+        let bitmap = Bitmap::new(data, rect(right - left, bottom - top));
+        Mask::new(bitmap, point(left as f64, top as f64), baseline)
+    }
+}
+
+// a piece of the background drawn again over whatever stands behind it
+#[derive(Debug, PartialEq)]
+pub struct Mask {
+    bitmap: Bitmap,
+    position: Point,
+    pub baseline: f64,
+}
+impl Mask {
+    fn new(bitmap: Bitmap, position: Point, baseline: f64) -> Self {
+        Self {
+            bitmap,
+            position,
+            baseline,
+        }
     }
     pub fn draw(&self, buffer: &mut Buffer) {
-        if let Image::Static(i) = &self.image {
-            let b = i.data();
-            buffer.draw_raw_slice(b.data());
+        buffer.draw_bmp(&self.bitmap, self.position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        buffer::Buffer,
+        geometry::{point, rect, Polygon},
+        image::Image,
+        snapshot::assert_snapshot,
+    };
+
+    use super::{Depth, Layer, Scenery};
+
+    fn background() -> Scenery {
+        Scenery::new(Image::load("resources/Pixel_Art_Background.png").unwrap())
+    }
+
+    #[test]
+    fn test_mask() {
+        let scenery = background();
+        let area = Polygon::new(vec![
+            point(10.0, 10.0),
+            point(20.0, 10.0),
+            point(10.0, 20.0),
+        ]);
+        let mask = scenery.mask(&area, 20.0);
+        assert_eq!(mask.position, point(10.0, 10.0));
+        assert_eq!((mask.bitmap.cols(), mask.bitmap.rows()), (10, 10));
+        let alpha = |x: usize, y: usize| mask.bitmap.data()[(y * 10 + x) * 4 + 3];
+        assert_eq!(alpha(1, 1), 255);
+        assert_eq!(alpha(9, 9), 0);
+
+        let area = Polygon::new(vec![
+            point(-10.0, -10.0),
+            point(5.0, -10.0),
+            point(5.0, 5.0),
+        ]);
+        let mask = scenery.mask(&area, 5.0);
+        assert_eq!((mask.bitmap.cols(), mask.bitmap.rows()), (5, 5));
+    }
+
+    #[test]
+    fn test_parallax() {
+        let fox = Image::load("resources/fox.png").unwrap();
+        let layer = |scroll: f64| Layer::new(fox.clone(), point(10.0, 0.0), scroll, Depth::Back);
+        let drawn = |layer: &Layer, camera: f64| {
+            let mut buffer = Buffer::new(rect(100, 60));
+            buffer.set_camera(point(camera, 0.0));
+            layer.draw(&mut buffer);
+            buffer.data().to_vec()
+        };
+        let at = |x: f64| {
+            let mut buffer = Buffer::new(rect(100, 60));
+            fox.draw(&mut buffer, point(x, 0.0));
+            buffer.data().to_vec()
+        };
+
+        // the camera moving 20 to the right moves the layer left by less in
+        // the distance and by more up close
+        assert_eq!(drawn(&layer(1.0), 20.0), at(-10.0));
+        assert_eq!(drawn(&layer(0.5), 20.0), at(0.0));
+        assert_eq!(drawn(&layer(0.0), 20.0), at(10.0));
+        assert_eq!(drawn(&layer(1.5), 20.0), at(-20.0));
+    }
+
+    #[test]
+    fn test_layer_order() {
+        let fox = Image::load("resources/fox.png").unwrap();
+        let mut scenery = background();
+        let layer = |depth: Depth| Layer::new(fox.clone(), point(0.0, 0.0), 1.0, depth);
+        scenery.layers.push(layer(Depth::Front));
+        scenery.layers.push(layer(Depth::Behind));
+        let mut buffer = Buffer::new(rect(640, 465));
+        // the opaque background hides the layer behind it
+        scenery.draw(&mut buffer);
+        let mut background = Buffer::new(rect(640, 465));
+        self::background().draw(&mut background);
+        assert_eq!(buffer.data(), background.data());
+        scenery.draw_front(&mut buffer);
+        assert_ne!(buffer.data(), background.data());
+    }
+
+    #[test]
+    fn test_animated_background() {
+        let image = Image::load("resources/fox_walk.png").unwrap();
+        let size = image.size();
+        let mut scenery = Scenery::new(image);
+        let drawn = |scenery: &Scenery| {
+            let mut buffer = Buffer::new(size);
+            scenery.draw(&mut buffer);
+            buffer
+        };
+        let first = drawn(&scenery);
+        assert!(first.data().iter().any(|&b| b != 0));
+        // into the second frame
+        while scenery.image.animation().unwrap().position().0 == 0 {
+            scenery.tick(Duration::from_millis(10));
         }
+        let later = drawn(&scenery);
+        assert_ne!(first.data(), later.data());
+        assert_snapshot("animated_background", &later, 2);
     }
 }
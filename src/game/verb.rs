@@ -0,0 +1,118 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::Deserialize;
+
+use crate::geometry::Point;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum Verb {
+    Walk,
+    LookAt,
+    PickUp,
+    Use,
+    TalkTo,
+}
+impl Verb {
+    pub const ALL: [Verb; 5] = [
+        Verb::Walk,
+        Verb::LookAt,
+        Verb::PickUp,
+        Verb::Use,
+        Verb::TalkTo,
+    ];
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Walk => "Walk to",
+            Self::LookAt => "Look at",
+            Self::PickUp => "Pick up",
+            Self::Use => "Use",
+            Self::TalkTo => "Talk to",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum Response {
+    Say(String),
+    // takes the object out of the room and gives the player the named item
+    PickUp(String),
+    // starts the named conversation
+    Talk(String),
+    // runs the script file
+    Run(PathBuf),
+}
+
+// what happens when the player uses a verb on something in the room
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Interaction {
+    pub name: String,
+    pub walk_to: Option<Point>,
+    pub responses: HashMap<Verb, Response>,
+    pub uses: HashMap<String, Response>,
+}
+impl Interaction {
+    pub fn new(
+        name: &str,
+        walk_to: Option<Point>,
+        responses: HashMap<Verb, Response>,
+        uses: HashMap<String, Response>,
+    ) -> Self {
+        Self {
+            name: name.to_owned(),
+            walk_to,
+            responses,
+            uses,
+        }
+    }
+    pub fn respond(&self, verb: Verb) -> Option<Response> {
+        if let Some(r) = self.responses.get(&verb) {
+            return Some(r.clone());
+        }
+        let fallback = match verb {
+            Verb::Walk => return None,
+            Verb::LookAt => format!("It's a {}.", self.name),
+            Verb::PickUp => "I can't pick that up.".to_owned(),
+            Verb::Use => "I can't use that.".to_owned(),
+            Verb::TalkTo => "It doesn't talk back.".to_owned(),
+        };
+        Some(Response::Say(fallback))
+    }
+    // using an inventory item on this
+    pub fn respond_to_item(&self, item: &str) -> Response {
+        self.uses
+            .get(item)
+            .cloned()
+            .unwrap_or_else(|| Response::Say("That doesn't work.".to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Interaction, Response, Verb};
+
+    #[test]
+    fn test_respond() {
+        let responses = HashMap::from([(Verb::LookAt, Response::Say("Round.".to_owned()))]);
+        let uses = HashMap::from([("stick".to_owned(), Response::Say("Poke.".to_owned()))]);
+        let i = Interaction::new("ball", None, responses, uses);
+        assert_eq!(
+            i.respond(Verb::LookAt),
+            Some(Response::Say("Round.".to_owned()))
+        );
+        assert_eq!(
+            i.respond(Verb::PickUp),
+            Some(Response::Say("I can't pick that up.".to_owned()))
+        );
+        assert_eq!(i.respond(Verb::Walk), None);
+        assert_eq!(
+            i.respond_to_item("stick"),
+            Response::Say("Poke.".to_owned())
+        );
+        assert_eq!(
+            i.respond_to_item("ball"),
+            Response::Say("That doesn't work.".to_owned())
+        );
+    }
+}
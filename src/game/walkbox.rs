@@ -1,65 +1,376 @@
+use std::collections::HashSet;
+
 use crate::geometry::{line_segment, point, LineSegment, Point, Polygon};
 
+// how big actors are drawn depending on how far up the screen they stand:
+// `far` and `near` are (y, scale) pairs, linearly interpolated in between
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ScaleZone {
+    pub far: (f64, f64),
+    pub near: (f64, f64),
+}
+impl ScaleZone {
+    pub fn at(&self, y: f64) -> f64 {
+        let ((y0, s0), (y1, s1)) = (self.far, self.near);
+        if y1 == y0 {
+            return s1;
+        }
+        let t = ((y - y0) / (y1 - y0)).clamp(0.0, 1.0);
+        s0 + (s1 - s0) * t
+    }
+}
+
+// how far from an edge a point still counts as on it
+const EDGE_TOLERANCE: f64 = 1e-6;
+
+// one walkable polygon: inside the exterior but outside every interior one,
+// the obstacles like tables standing in the room
 #[derive(Default, Debug, PartialEq, Clone)]
-pub struct WalkBox {
-    pub exterior: Polygon,
+struct Area {
+    name: String,
+    exterior: Polygon,
     interior: Vec<Polygon>,
-    xmin: f64,
-    xmax: f64,
-    ymin: f64,
-    ymax: f64,
+    enabled: bool,
+    min: Point,
+    max: Point,
 }
-impl WalkBox {
-    pub fn new(exterior: Polygon, interior: Vec<Polygon>) -> Self {
-        let mut xmin = f64::MAX;
-        let mut xmax = 0.0;
-        let mut ymin = f64::MAX;
-        let mut ymax = 0.0;
+impl Area {
+    // the polygons can be written either way round, they're stored clockwise
+    fn new(name: &str, exterior: Polygon, interior: Vec<Polygon>, enabled: bool) -> Self {
+        let clockwise = |p: Polygon| if p.is_clockwise() { p } else { p.reversed() };
+        let exterior = clockwise(exterior);
+        let interior: Vec<_> = interior.into_iter().map(clockwise).collect();
+        let mut min = point(f64::MAX, f64::MAX);
+        let mut max = point(f64::MIN, f64::MIN);
         for v in &exterior.vertices {
-            if v.x > xmax {
-                xmax = v.x
-            } else if v.x < xmin {
-                xmin = v.x
-            }
-            if v.y > ymax {
-                ymax = v.y
-            } else if v.y < ymin {
-                ymin = v.y
-            }
+            min = point(min.x.min(v.x), min.y.min(v.y));
+            max = point(max.x.max(v.x), max.y.max(v.y));
         }
         Self {
+            name: name.to_owned(),
             exterior,
             interior,
-            xmin,
-            xmax,
-            ymin,
-            ymax,
+            enabled,
+            min,
+            max,
         }
     }
-    pub fn concave_vertexes(&self) -> impl Iterator<Item = Point> + '_ {
+    // the corners a shortest path can bend around: the exterior's inward
+    // ones and the obstacles' outward ones
+    fn corners(&self) -> impl Iterator<Item = Point> + '_ {
         let internal_vertices = self.exterior.concave_vertices();
         internal_vertices.chain(self.interior.iter().flat_map(|elem| elem.convex_vertices()))
     }
-    pub fn edges(&self) -> impl Iterator<Item = LineSegment> + '_ {
+    fn vertices(&self) -> impl Iterator<Item = Point> + '_ {
+        let vertices = self.exterior.vertices.iter();
+        vertices
+            .chain(self.interior.iter().flat_map(|h| h.vertices.iter()))
+            .copied()
+    }
+    fn edges(&self) -> impl Iterator<Item = LineSegment> + '_ {
         let internal_edges = self.exterior.edges();
         internal_edges.chain(self.interior.iter().flat_map(|elem| elem.edges()))
     }
-    pub fn intersects(&self, ls: &LineSegment) -> bool {
-        self.edges().any(|e| ls.crosses(&e))
-    }
-    pub fn contains(&self, p: Point) -> bool {
-        if p.x <= self.xmin || p.x >= self.xmax || p.y <= self.ymin || p.y >= self.ymax {
+    fn contains(&self, p: Point) -> bool {
+        if p.x <= self.min.x || p.x >= self.max.x || p.y <= self.min.y || p.y >= self.max.y {
             return false;
         }
-        let ray_start = point(self.xmin - f64::EPSILON, self.ymin - f64::EPSILON);
-        let ls = line_segment(ray_start, p);
+        self.exterior.contains(p) && !self.interior.iter().any(|h| h.contains(p))
+    }
+    fn on_edge(&self, p: Point) -> bool {
+        self.edges()
+            .any(|e| (e.closest_point(p) - p).length() < EDGE_TOLERANCE)
+    }
+}
 
-        let count = self.edges().fold(0, |mut acc, e| {
-            if ls.intersects3(&e) {
-                acc += 1;
+// where actors can walk: one or more areas, joined wherever they touch or
+// overlap, any of which game logic can switch off and on again, like the
+// floor past a door that's still shut
+#[derive(Default, Debug, PartialEq, Clone)]
+pub struct WalkBox {
+    pub scale: Option<ScaleZone>,
+    areas: Vec<Area>,
+}
+impl WalkBox {
+    // a walkbox of one area that's always there
+    pub fn new(exterior: Polygon, interior: Vec<Polygon>) -> Self {
+        Self {
+            scale: None,
+            areas: vec![Area::new("", exterior, interior, true)],
+        }
+    }
+    pub fn add_area(
+        &mut self,
+        name: &str,
+        exterior: Polygon,
+        interior: Vec<Polygon>,
+        enabled: bool,
+    ) {
+        self.areas
+            .push(Area::new(name, exterior, interior, enabled));
+    }
+    // the named area's bounding box if there is one, everything that changed
+    // by switching it lies in there
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Option<(Point, Point)> {
+        let area = self
+            .areas
+            .iter_mut()
+            .find(|a| !name.is_empty() && a.name == name)?;
+        area.enabled = enabled;
+        Some((area.min, area.max))
+    }
+    // whether each named area is switched on
+    pub fn enabled(&self) -> impl Iterator<Item = (&str, bool)> + '_ {
+        self.areas
+            .iter()
+            .filter(|a| !a.name.is_empty())
+            .map(|a| (a.name.as_str(), a.enabled))
+    }
+    // the name, whether it's switched on, the exterior and obstacles of every
+    // area
+    pub fn polygons(&self) -> impl Iterator<Item = (&str, bool, &Polygon, &[Polygon])> + '_ {
+        self.areas.iter().map(|a| {
+            (
+                a.name.as_str(),
+                a.enabled,
+                &a.exterior,
+                a.interior.as_slice(),
+            )
+        })
+    }
+    fn areas(&self) -> impl Iterator<Item = &Area> + '_ {
+        self.areas.iter().filter(|a| a.enabled)
+    }
+    // the corners of every area, where areas join the corners of one lying on
+    // another and the points their edges cross; some of these aren't corners
+    // of the whole, which costs a few graph edges but never a wrong path
+    pub fn concave_vertexes(&self) -> Vec<Point> {
+        let mut corners: Vec<Point> = self.areas().flat_map(Area::corners).collect();
+        for (i, a) in self.areas().enumerate() {
+            for b in self.areas().skip(i + 1) {
+                let joined = |x: &Area, y: &Area| {
+                    x.vertices()
+                        .filter(|v| y.contains(*v) || y.on_edge(*v))
+                        .collect::<Vec<_>>()
+                };
+                corners.extend(joined(a, b));
+                corners.extend(joined(b, a));
+                for e in a.edges() {
+                    corners.extend(b.edges().filter_map(|f| e.crossing(&f)));
+                }
+            }
+        }
+        let mut seen = HashSet::new();
+        corners.retain(|c| seen.insert(*c));
+        corners
+    }
+    pub fn edges(&self) -> impl Iterator<Item = LineSegment> + '_ {
+        self.areas().flat_map(Area::edges)
+    }
+    // whether walking straight from `a` to `b` stays in the walkable area:
+    // the line is cut wherever it crosses an edge or passes a corner, and
+    // every piece has to be walkable in the middle. that rules out cutting
+    // across an obstacle from corner to corner and lets lines run from one
+    // area into the next
+    pub fn sees(&self, a: Point, b: Point) -> bool {
+        let ls = line_segment(a, b);
+        let length = ls.length();
+        if length < f64::EPSILON {
+            return self.walkable(a);
+        }
+        let mut cuts: Vec<f64> = vec![0.0, 1.0];
+        for e in self.edges() {
+            if let Some(c) = ls.crossing(&e) {
+                cuts.push((c - a).length() / length);
+            }
+        }
+        for area in self.areas() {
+            for v in area.vertices() {
+                let c = ls.closest_point(v);
+                if (c - v).length() < EDGE_TOLERANCE {
+                    cuts.push((c - a).length() / length);
+                }
             }
-            acc
+        }
+        cuts.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        cuts.windows(2)
+            .all(|w| self.walkable(a + (b - a) * ((w[0] + w[1]) / 2.0)))
+    }
+    pub fn scale_at(&self, p: Point) -> f64 {
+        self.scale.map_or(1.0, |s| s.at(p.y))
+    }
+    // strictly inside the walkable area
+    pub fn contains(&self, p: Point) -> bool {
+        self.areas().any(|a| a.contains(p))
+    }
+    // inside the walkable area or on its edge
+    pub fn walkable(&self, p: Point) -> bool {
+        self.contains(p) || self.areas().any(|a| a.on_edge(p))
+    }
+    // `p` itself if it can be walked to, otherwise the nearest point on an
+    // edge that can, which for a point on an obstacle is its nearest side
+    pub fn closest_walkable(&self, p: Point) -> Point {
+        if self.walkable(p) {
+            return p;
+        }
+        self.edges()
+            .map(|e| e.closest_point(p))
+            .filter(|c| self.walkable(*c))
+            .map(|c| ((c - p).length(), c))
+            .fold((f64::MAX, p), |acc, c| if c.0 < acc.0 { c } else { acc })
+            .1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::{point, Polygon};
+
+    use super::{ScaleZone, WalkBox};
+
+    // a 100x100 room with a table in the middle, the table written
+    // anticlockwise
+    fn room_with_table() -> WalkBox {
+        WalkBox::new(
+            Polygon::new(vec![
+                point(0.0, 0.0),
+                point(100.0, 0.0),
+                point(100.0, 100.0),
+                point(0.0, 100.0),
+            ]),
+            vec![Polygon::new(vec![
+                point(40.0, 40.0),
+                point(40.0, 60.0),
+                point(60.0, 60.0),
+                point(60.0, 40.0),
+            ])],
+        )
+    }
+
+    #[test]
+    fn test_contains() {
+        let walkbox = room_with_table();
+        assert!(walkbox.contains(point(20.0, 50.0)));
+        assert!(!walkbox.contains(point(50.0, 50.0)));
+        assert!(!walkbox.contains(point(150.0, 50.0)));
+
+        // the table's edge can be walked along but not its inside
+        assert!(walkbox.walkable(point(40.0, 50.0)));
+        assert!(!walkbox.walkable(point(41.0, 50.0)));
+        assert!(walkbox.walkable(point(0.0, 50.0)));
+    }
+
+    #[test]
+    fn test_bounds() {
+        // the first corner is the furthest left and up, it has to count
+        // towards the bounds as well as the furthest right and down
+        let walkbox = WalkBox::new(
+            Polygon::new(vec![
+                point(0.0, 0.0),
+                point(100.0, 50.0),
+                point(50.0, 100.0),
+            ]),
+            vec![],
+        );
+        assert!(walkbox.contains(point(10.0, 10.0)));
+        assert!(walkbox.contains(point(60.0, 60.0)));
+        assert!(!walkbox.contains(point(90.0, 10.0)));
+    }
+
+    #[test]
+    fn test_sees() {
+        let walkbox = room_with_table();
+        assert!(!walkbox.sees(point(20.0, 50.0), point(80.0, 50.0)));
+        assert!(walkbox.sees(point(20.0, 20.0), point(80.0, 20.0)));
+        // along a side of the table, but not across it corner to corner
+        assert!(walkbox.sees(point(40.0, 40.0), point(60.0, 40.0)));
+        assert!(!walkbox.sees(point(40.0, 40.0), point(60.0, 60.0)));
+        // nor through two of its corners from further away, where no edge is
+        // crossed and the middle of the line is outside the table
+        assert!(!walkbox.sees(point(30.0, 30.0), point(99.0, 99.0)));
+        assert!(!walkbox.sees(point(30.0, 70.0), point(95.0, 5.0)));
+
+        let mut corners = walkbox.concave_vertexes();
+        corners.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(
+            corners,
+            vec![
+                point(40.0, 40.0),
+                point(40.0, 60.0),
+                point(60.0, 40.0),
+                point(60.0, 60.0)
+            ]
+        );
+    }
+
+    // the room with a bridge off its right side, switched off
+    fn room_with_bridge() -> WalkBox {
+        let mut walkbox = room_with_table();
+        walkbox.add_area(
+            "bridge",
+            Polygon::new(vec![
+                point(100.0, 40.0),
+                point(200.0, 40.0),
+                point(200.0, 60.0),
+                point(100.0, 60.0),
+            ]),
+            vec![],
+            false,
+        );
+        walkbox
+    }
+
+    #[test]
+    fn test_areas() {
+        let mut walkbox = room_with_bridge();
+        let (inside, across) = (point(20.0, 50.0), point(190.0, 50.0));
+        assert!(!walkbox.walkable(across));
+        assert_eq!(walkbox.closest_walkable(across), point(100.0, 50.0));
+        assert!(walkbox.set_enabled("", false).is_none());
+
+        walkbox.set_enabled("bridge", true);
+        assert!(walkbox.contains(across));
+        assert!(walkbox.walkable(point(100.0, 50.0)));
+        assert!(walkbox.sees(point(80.0, 50.0), across));
+        // over the room's side past the end of the bridge
+        assert!(!walkbox.sees(point(80.0, 20.0), across));
+        assert!(!walkbox.sees(inside, across));
+
+        // where the bridge meets the room is a corner to walk around
+        let corners = walkbox.concave_vertexes();
+        assert!(corners.contains(&point(100.0, 40.0)));
+        assert!(corners.contains(&point(100.0, 60.0)));
+        assert_eq!(
+            walkbox.enabled().collect::<Vec<_>>(),
+            vec![("bridge", true)]
+        );
+    }
+
+    #[test]
+    fn test_closest_walkable() {
+        let walkbox = room_with_table();
+        let p = point(20.0, 30.0);
+        assert_eq!(walkbox.closest_walkable(p), p);
+        assert_eq!(
+            walkbox.closest_walkable(point(45.0, 52.0)),
+            point(40.0, 52.0)
+        );
+        assert_eq!(
+            walkbox.closest_walkable(point(120.0, 50.0)),
+            point(100.0, 50.0)
+        );
+    }
+
+    #[test]
+    fn test_scale_at() {
+        let mut walkbox = WalkBox::default();
+        assert_eq!(walkbox.scale_at(point(0.0, 300.0)), 1.0);
+        walkbox.scale = Some(ScaleZone {
+            far: (200.0, 0.5),
+            near: (400.0, 1.0),
         });
-        (count % 2) == 0
+        assert_eq!(walkbox.scale_at(point(0.0, 100.0)), 0.5);
+        assert_eq!(walkbox.scale_at(point(0.0, 300.0)), 0.75);
+        assert_eq!(walkbox.scale_at(point(0.0, 500.0)), 1.0);
     }
 }
@@ -1,75 +1,167 @@
-use std::time::{Duration, Instant};
+use std::{
+    collections::{BTreeSet, HashMap},
+    mem,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
-use winit::event::ElementState;
+use winit::event::{ElementState, VirtualKeyCode};
 
 use crate::{
+    assets::Assets,
     buffer::Buffer,
-    game::{Actor, Object, Scenery, ShortestPath, Updatable, WalkBox},
-    geometry::{point, Graph, LineType, Point, Polygon},
-    text::GlyphWriter,
+    cutscene::{Cutscene, Timeline},
+    error::Error,
+    game::{
+        Actor, AnimationSet, Camera, Choice, Depth, Dialogue, Effect, Inventory, Item, Node,
+        Response, Rooms, ShortestPath, Updatable, Verb,
+    },
+    geometry::{point, LineType, Point, Rect},
+    image::Control,
+    save::{self, SaveGame, SAVE_VERSION},
+    scene::{self, GameData},
+    script::{Host, Script},
+    text::{GlyphWriter, FONT_PATH},
 };
 
 pub const TICK: Duration = Duration::from_millis(1000 / 90);
+pub const SCREEN: Rect = Rect { w: 640, h: 465 };
+
+// the user interface along the bottom of the screen, measured up from it
+const SENTENCE_LINE: f64 = 27.0;
+const VERB_BAR: f64 = 14.0;
+const SAVE_DIR: &str = "saves";
+
+// something in the current room the player can use a verb on, by name so it
+// can't turn into something else while the player walks over
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    Object(String),
+    Actor(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Drawable {
+    Character,
+    Object(usize),
+    Actor(usize),
+    Mask(usize),
+    Layer(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Action {
+    verb: Verb,
+    target: Target,
+    // the inventory item being used on the target, if any
+    item: Option<String>,
+}
+
+#[derive(Debug)]
+struct Speech {
+    text: String,
+    remaining: Duration,
+    // the actor in the current room doing the talking, the player if none
+    speaker: Option<usize>,
+}
+impl Speech {
+    fn new(text: String, speaker: Option<usize>) -> Self {
+        let remaining = Duration::from_millis(1000 + 60 * text.len() as u64);
+        Self {
+            text,
+            remaining,
+            speaker,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Conversation {
+    dialogue: String,
+    node: String,
+    speaker: Option<usize>,
+}
 
 #[derive(Debug)]
 pub struct GameState {
     pub exit_requested: bool,
+    // the walkbox, paths and mouse position drawn over the frame
+    pub debug_overlay: bool,
+    // the game data, read again when reloading
+    path: PathBuf,
     previous_time: Instant,
+    // on the screen, `camera` turns it into a position in the room
     mouse_location: Point,
     mouse_click: bool,
+    screen: Rect,
+    camera: Camera,
     character: Actor,
     character_destimation: Option<Point>,
     character_path: Option<ShortestPath>,
-    actors: Vec<Actor>,
-    objects: Vec<Object>,
-    scenery: Scenery,
-    walkbox: WalkBox,
+    verb: Verb,
+    pending: Option<Action>,
+    held: Option<String>,
+    speech: Option<Speech>,
+    conversation: Option<Conversation>,
+    dialogues: HashMap<String, Dialogue>,
+    scripts: Vec<Script>,
+    cutscene: Option<Cutscene>,
+    fade: f64,
+    inventory: Inventory,
+    flags: BTreeSet<String>,
+    rooms: Rooms,
+    assets: Assets,
+    save_dir: PathBuf,
+    slot: usize,
     text_writer: GlyphWriter,
-    graph: Graph,
 }
 impl GameState {
-    pub fn new() -> Self {
-        let character_image = "resources/fox.png";
-        // let ball_image = "resources/ball.png";
-        let character = Actor::new(character_image, point(150.0, 150.0), Some(0.15));
-        // let objects = vec![Object::new(ball_image, point(350.0, 350.0))];
-        let scenery = Scenery::new();
-        let walkbox = WalkBox::new(
-            Polygon::new(vec![
-                point(60.0, 60.0),
-                point(300.0, 60.0),
-                point(300.0, 240.0),
-                point(360.0, 240.0),
-                point(360.0, 60.0),
-                point(610.0, 60.0),
-                point(610.0, 260.0),
-                point(510.0, 260.0),
-                point(510.0, 280.0),
-                point(610.0, 280.0),
-                point(610.0, 435.0),
-                point(60.0, 435.0),
-                point(60.0, 60.0),
-            ]),
-            vec![],
-        );
-        let text_writer = GlyphWriter::new();
-        let graph = Graph::new(walkbox.clone());
+    pub fn new<T: AsRef<Path>>(path: T) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data: GameData = scene::load(path)?;
+        let mut assets = Assets::default();
+        let inventory = load_inventory(path, &data, &mut assets)?;
+        let dialogues = load_dialogues(&data, &inventory)?;
+        let rooms = Rooms::new(data.rooms, &data.start, &mut assets)?;
+        let character_image = assets.image(scene::asset(&data.character.image)?);
+        let spawn = rooms.current().spawn;
+        let mut character = Actor::new(character_image, spawn, Some(data.character.speed));
+        character.animations = AnimationSet::load(&data.character.animations, &mut assets)?;
+        let text_writer = assets.font(FONT_PATH)?;
+        let scripts = rooms.current().on_enter.clone().map(Script::new);
+        let mut camera = Camera::new(SCREEN);
+        camera.set_bounds(rooms.current().scenery.size());
+        camera.center_on(spawn);
 
-        Self {
+        Ok(Self {
             exit_requested: false,
+            debug_overlay: cfg!(debug_assertions),
+            path: path.to_owned(),
             previous_time: Instant::now(),
+            screen: SCREEN,
+            assets,
+            camera,
             character,
             character_path: None,
             character_destimation: None,
-            actors: vec![],
-            objects: vec![],
-            scenery,
+            verb: Verb::Walk,
+            pending: None,
+            held: None,
+            speech: None,
+            conversation: None,
+            dialogues,
+            scripts: scripts.into_iter().collect(),
+            cutscene: None,
+            fade: 0.0,
+            inventory,
+            flags: BTreeSet::new(),
+            save_dir: PathBuf::from(SAVE_DIR),
+            slot: 1,
             mouse_location: point(0.0, 0.0),
             mouse_click: false,
             text_writer,
-            walkbox,
-            graph,
-        }
+            rooms,
+        })
     }
     pub fn mouse_over(&mut self, loc: Point) {
         self.mouse_location = loc;
@@ -79,69 +171,218 @@ impl GameState {
             self.mouse_click = true;
         }
     }
+    // 1-5 pick a verb, F1-F4 pick a save slot, F5 saves and F9 loads it;
+    // during a cutscene only space does anything, and skips it
+    pub fn key_pressed(&mut self, key: VirtualKeyCode) {
+        if self.cutscene.is_some() {
+            if key == VirtualKeyCode::Space {
+                self.skip_cutscene();
+            }
+            return;
+        }
+        let verb = match key {
+            VirtualKeyCode::Key1 => Verb::Walk,
+            VirtualKeyCode::Key2 => Verb::LookAt,
+            VirtualKeyCode::Key3 => Verb::PickUp,
+            VirtualKeyCode::Key4 => Verb::Use,
+            VirtualKeyCode::Key5 => Verb::TalkTo,
+            VirtualKeyCode::F1 | VirtualKeyCode::F2 | VirtualKeyCode::F3 | VirtualKeyCode::F4 => {
+                self.slot = key as usize - VirtualKeyCode::F1 as usize + 1;
+                self.say(format!("Slot {}.", self.slot));
+                return;
+            }
+            VirtualKeyCode::F5 => {
+                match self.save(self.slot) {
+                    Ok(()) => self.say(format!("Saved to slot {}.", self.slot)),
+                    Err(e) => eprintln!("{}", e),
+                }
+                return;
+            }
+            VirtualKeyCode::F9 => {
+                match self.load(self.slot) {
+                    Ok(()) => self.say(format!("Loaded slot {}.", self.slot)),
+                    Err(e) => eprintln!("{}", e),
+                }
+                return;
+            }
+            _ => return,
+        };
+        self.verb = verb;
+    }
+    pub fn save(&self, slot: usize) -> Result<(), Error> {
+        save::write(save::slot_path(&self.save_dir, slot), &self.save_game())
+    }
+    // nothing changes unless the whole save could be read and applied
+    pub fn load(&mut self, slot: usize) -> Result<(), Error> {
+        let save = save::read(save::slot_path(&self.save_dir, slot))?;
+        self.restore(&save)
+    }
+    // swaps files that changed on disk into the running game, leaving
+    // everyone where they are; if anything fails to load it's reported and
+    // nothing changes
+    pub fn reload(&mut self, changed: &[PathBuf]) {
+        self.assets.forget(changed);
+        if let Err(e) = self.try_reload() {
+            eprintln!("{}", e);
+        }
+    }
+    fn try_reload(&mut self) -> Result<(), Error> {
+        let data: GameData = scene::load(&self.path)?;
+        let image = self.assets.image(scene::asset(&data.character.image)?);
+        let animations = AnimationSet::load(&data.character.animations, &mut self.assets)?;
+        let text_writer = self.assets.font(FONT_PATH)?;
+        let mut inventory = load_inventory(&self.path, &data, &mut self.assets)?;
+        let dialogues = load_dialogues(&data, &inventory)?;
+        self.rooms.reload(&mut self.assets)?;
+
+        let items: Vec<_> = self.inventory.items().map(|i| i.to_owned()).collect();
+        inventory.set_items(&items);
+        self.inventory = inventory;
+        self.dialogues = dialogues;
+        self.character.image = image;
+        self.character.animations = animations;
+        self.text_writer = text_writer;
+        // what was clicked on may have gone or changed with the room
+        self.mouse_click = false;
+        self.pending = None;
+        self.held = None;
+        self.camera.set_bounds(self.rooms.current().scenery.size());
+        self.assets.collect();
+        Ok(())
+    }
+
+    fn save_game(&self) -> SaveGame {
+        SaveGame {
+            version: SAVE_VERSION,
+            room: self.rooms.current_name().to_owned(),
+            character: self.character.state(),
+            rooms: self.rooms.states(),
+            inventory: self.inventory.items().map(|i| i.to_owned()).collect(),
+            flags: self.flags.clone(),
+        }
+    }
+    fn restore(&mut self, save: &SaveGame) -> Result<(), Error> {
+        self.rooms
+            .restore(&save.room, &save.rooms, &mut self.assets)?;
+        self.character.restore(&save.character);
+        self.inventory.set_items(&save.inventory);
+        self.flags = save.flags.clone();
+        self.character_path = None;
+        self.character_destimation = None;
+        self.mouse_click = false;
+        self.pending = None;
+        self.held = None;
+        self.speech = None;
+        self.conversation = None;
+        self.cutscene = None;
+        self.fade = 0.0;
+        self.verb = Verb::Walk;
+        self.reset_camera();
+        Ok(())
+    }
     pub fn tick(&mut self) -> bool {
         let delta = self.previous_time.elapsed();
         if delta >= TICK {
             self.previous_time = Instant::now();
+            self.update(delta);
+        }
+        delta >= TICK
+    }
+    // advances the world by `delta`, independent of the wall clock
+    pub fn update(&mut self, delta: Duration) {
+        let mouse = self.camera.to_world(self.mouse_location);
+        if self.debug_overlay {
+            let dest_point = self.calculate_destination(mouse);
+            self.character_destimation = Some(dest_point);
+            let from = self.character.location;
+            self.character_path = self.rooms.current_mut().path(from, dest_point);
+        }
 
-            if cfg!(debug_assertions) {
-                let dest_point = self.calculate_destination();
-                self.character_destimation = Some(dest_point);
-                self.graph
-                    .add_temporary_edges(self.character.location, dest_point);
-                self.character_path = self.graph.path_to(self.character.location, dest_point);
+        if self.mouse_click {
+            self.mouse_click = false;
+            self.click();
+        }
+
+        self.run_scripts(delta);
+        let speaker = self.speech.as_ref().map(|s| s.speaker);
+        self.character.talking = speaker == Some(None);
+        for (i, a) in self.rooms.current_mut().actors.iter_mut().enumerate() {
+            a.talking = speaker == Some(Some(i));
+        }
+        if let Some(mut cutscene) = self.cutscene.take() {
+            if cutscene.tick(self, delta) {
+                self.cutscene = Some(cutscene);
             }
+        }
 
-            if self.mouse_click {
-                self.mouse_click = false;
-                if !cfg!(debug_assertions) {
-                    let dest_point = self.calculate_destination();
-                    self.graph
-                        .add_temporary_edges(self.character.location, dest_point);
-                    self.character_path = self.graph.path_to(self.character.location, dest_point);
-                }
-                if let Some(path) = &self.character_path {
-                    self.character.set_path(path.points().map(|e| e.to_owned()));
-                }
+        let previous_location = self.character.location;
+        self.character.mouse_over(mouse);
+        self.character.tick(delta);
+        self.check_exits(previous_location);
+        if self.character.path.is_empty() {
+            if let Some(action) = self.pending.take() {
+                self.interact(action);
+            }
+        }
+        if let Some(speech) = &mut self.speech {
+            speech.remaining = speech.remaining.saturating_sub(delta);
+            if speech.remaining.is_zero() {
+                self.speech = None;
             }
+        }
 
-            self.character.mouse_over(self.mouse_location);
-            self.character.tick(delta);
+        self.rooms.current_mut().objects.iter_mut().for_each(|s| {
+            s.mouse_over(mouse);
+            s.tick(delta);
+        });
 
-            self.objects.iter_mut().for_each(|s| {
-                s.mouse_over(self.mouse_location);
-                s.tick(delta);
-            });
+        self.rooms.current_mut().actors.iter_mut().for_each(|s| {
+            s.mouse_over(mouse);
+            s.tick(delta);
+        });
 
-            self.actors.iter_mut().for_each(|s| {
-                s.mouse_over(self.mouse_location);
-                s.tick(delta);
-            });
+        let room = self.rooms.current_mut();
+        room.scenery.tick(delta);
+        self.character.scale = room.walkbox.scale_at(self.character.location);
+        for a in room.actors.iter_mut() {
+            a.scale = room.walkbox.scale_at(a.location);
         }
-        delta >= TICK
+        self.camera.follow(self.character.location, delta);
+    }
+
+    // the logical resolution, `draw` expects a buffer this size
+    pub fn set_screen(&mut self, screen: Rect) {
+        self.screen = screen;
+        self.camera.set_viewport(screen);
+        self.camera.center_on(self.character.location);
     }
 
     pub fn draw(&self, buffer: &mut Buffer) {
-        self.scenery.draw(buffer);
+        buffer.set_camera(self.camera.position());
+        self.rooms.current().scenery.draw(buffer);
 
-        if cfg!(debug_assertions) {
-            for l in self.walkbox.exterior.edges() {
+        if self.debug_overlay {
+            for l in self.rooms.current().walkbox.edges() {
                 buffer.draw_line(&l, crate::geometry::LineType::Box);
             }
-            for l in self.graph.walkable_edges() {
-                buffer.draw_line(l, LineType::Graph);
+            for l in self.rooms.current().navigation_lines() {
+                buffer.draw_line(&l, LineType::Graph);
             }
         }
 
-        self.character.draw(buffer);
-        self.objects.iter().for_each(|s| {
-            s.draw(buffer);
-        });
-        self.actors.iter().for_each(|s| {
-            s.draw(buffer);
-        });
+        let room = self.rooms.current();
+        for drawable in self.render_queue() {
+            match drawable {
+                Drawable::Character => self.character.draw(buffer),
+                Drawable::Object(i) => room.objects[i].draw(buffer),
+                Drawable::Actor(i) => room.actors[i].draw(buffer),
+                Drawable::Mask(i) => room.masks[i].draw(buffer),
+                Drawable::Layer(i) => room.scenery.layers[i].draw(buffer),
+            }
+        }
+        room.scenery.draw_front(buffer);
 
-        if cfg!(debug_assertions) {
+        if self.debug_overlay {
             if let (Some(path), Some(dest_point)) =
                 (&self.character_path, self.character_destimation)
             {
@@ -150,7 +391,33 @@ impl GameState {
                 }
                 buffer.draw_point(dest_point);
             }
+        }
+
+        // the rest is drawn on the screen rather than in the room
+        buffer.set_camera(point(0.0, 0.0));
+        if let Some(speech) = &self.speech {
+            let speaker = speech
+                .speaker
+                .and_then(|i| self.rooms.current().actors.get(i))
+                .unwrap_or(&self.character);
+            let bmp = self.text_writer.make_string(&speech.text).to_bmp();
+            let h = speaker.current_image().size().h as f64 * speaker.scale;
+            let p = self.camera.to_screen(speaker.location);
+            let x = (p.x - bmp.cols() as f64 / 2.0).max(0.0);
+            let y = (p.y - h - bmp.rows() as f64 - 4.0).max(0.0);
+            buffer.draw_bmp(&bmp, point(x, y));
+        }
+        if self.conversation.is_some() {
+            self.draw_choices(buffer);
+        } else if self.cutscene.is_none() {
+            self.draw_verbs(buffer);
+            self.inventory.draw(buffer);
+        }
+        if self.fade > 0.0 {
+            buffer.fade(self.fade);
+        }
 
+        if self.debug_overlay {
             let l = self.mouse_location;
             let to = self
                 .text_writer
@@ -161,21 +428,840 @@ impl GameState {
         }
     }
 
-    fn calculate_destination(&self) -> Point {
-        if self.walkbox.contains(self.mouse_location) {
-            return self.mouse_location;
+    // back to front: whatever stands lower on the screen is drawn later, ties
+    // keep the character under objects under actors under masks under layers
+    fn render_queue(&self) -> Vec<Drawable> {
+        let room = self.rooms.current();
+        let mut queue = vec![(self.character.baseline(), Drawable::Character)];
+        let objects = room.objects.iter().map(|o| o.baseline());
+        queue.extend(objects.enumerate().map(|(i, b)| (b, Drawable::Object(i))));
+        let actors = room.actors.iter().map(|a| a.baseline());
+        queue.extend(actors.enumerate().map(|(i, b)| (b, Drawable::Actor(i))));
+        let masks = room.masks.iter().map(|m| m.baseline);
+        queue.extend(masks.enumerate().map(|(i, b)| (b, Drawable::Mask(i))));
+        let layers = room.scenery.layers.iter().enumerate();
+        queue.extend(layers.filter_map(|(i, l)| match l.depth {
+            Depth::Baseline(b) => Some((b, Drawable::Layer(i))),
+            _ => None,
+        }));
+        queue.sort_by(|a, b| a.0.total_cmp(&b.0));
+        queue.into_iter().map(|(_, d)| d).collect()
+    }
+
+    fn click(&mut self) {
+        if self.cutscene.is_some() {
+            return;
+        }
+        if self.conversation.is_some() {
+            if let Some(choice) = self.choice_at(self.mouse_location) {
+                self.choose(choice);
+            }
+            return;
+        }
+        if let Some(verb) = self.verb_at(self.mouse_location) {
+            self.verb = verb;
+            self.held = None;
+            return;
+        }
+        if let Some(item) = self.inventory.slot_at(self.mouse_location, self.screen) {
+            self.click_item(item.to_owned());
+            return;
+        }
+        self.speech = None;
+        self.pending = None;
+        let mouse = self.camera.to_world(self.mouse_location);
+        let dest_point = match self.target_at(mouse) {
+            Some(target) => {
+                let point = self.interaction_point(&target).unwrap_or(mouse);
+                self.pending = Some(Action {
+                    verb: self.verb,
+                    target,
+                    item: self.held.take(),
+                });
+                point
+            }
+            None => {
+                self.held = None;
+                mouse
+            }
+        };
+        self.walk_to(self.calculate_destination(dest_point));
+        self.verb = Verb::Walk;
+    }
+
+    // inventory items are used straight away, no walking involved
+    fn click_item(&mut self, item: String) {
+        self.speech = None;
+        match (self.held.take(), self.verb) {
+            (Some(held), _) if held != item => {
+                if self.inventory.combine(&held, &item).is_none() {
+                    self.say("I can't combine those.".to_owned());
+                }
+            }
+            (Some(_), _) => {}
+            (None, Verb::Walk | Verb::Use) => self.held = Some(item),
+            (None, Verb::LookAt) => {
+                let text = self.inventory.item(&item).map(|i| i.description.clone());
+                self.say(text.unwrap_or_default());
+            }
+            (None, Verb::PickUp) => self.say("I already have it.".to_owned()),
+            (None, Verb::TalkTo) => self.say("It doesn't talk back.".to_owned()),
+        }
+        self.verb = Verb::Walk;
+    }
+
+    fn say(&mut self, text: String) {
+        self.speech = Some(Speech::new(text, None));
+    }
+
+    fn walk_to(&mut self, dest_point: Point) {
+        let from = self.character.location;
+        self.character_path = self.rooms.current_mut().path(from, dest_point);
+        if let Some(path) = &self.character_path {
+            self.character.set_path(path.points().map(|e| e.to_owned()));
+        }
+    }
+
+    // actors are drawn over objects, so they get hit first
+    fn target_at(&mut self, p: Point) -> Option<Target> {
+        let room = self.rooms.current_mut();
+        if let Some(a) = room
+            .actors
+            .iter_mut()
+            .rev()
+            .find_map(|a| a.mouse_click(p).then_some(a))
+        {
+            return a
+                .interaction
+                .as_ref()
+                .map(|i| Target::Actor(i.name.clone()));
+        }
+        room.objects
+            .iter_mut()
+            .rev()
+            .find_map(|o| o.mouse_click(p).then_some(o))
+            .map(|o| Target::Object(o.interaction().name.clone()))
+    }
+
+    fn hovered_target(&self) -> Option<&str> {
+        let room = self.rooms.current();
+        let actor = room.actors.iter().rev().find(|a| a.is_hovered());
+        if let Some(i) = actor.and_then(|a| a.interaction.as_ref()) {
+            return Some(&i.name);
+        }
+        let object = room.objects.iter().rev().find(|o| o.is_hovered());
+        object.map(|o| o.interaction().name.as_str())
+    }
+
+    // None once the target has gone from the room
+    fn interaction_point(&self, target: &Target) -> Option<Point> {
+        let room = self.rooms.current();
+        match target {
+            Target::Object(name) => room
+                .object_named(name)
+                .map(|i| room.objects[i].interaction_point()),
+            Target::Actor(name) => room
+                .actor_named(name)
+                .map(|i| room.actors[i].interaction_point()),
+        }
+    }
+
+    // does nothing if the target went while the player walked over
+    fn interact(&mut self, action: Action) {
+        let room = self.rooms.current();
+        let interaction = match &action.target {
+            Target::Object(name) => room
+                .object_named(name)
+                .map(|i| room.objects[i].interaction()),
+            Target::Actor(name) => room
+                .actor_named(name)
+                .and_then(|i| room.actors[i].interaction.as_ref()),
+        };
+        let response = match &action.item {
+            Some(item) => interaction.map(|i| i.respond_to_item(item)),
+            None => interaction.and_then(|i| i.respond(action.verb)),
+        };
+        match response {
+            Some(Response::Say(text)) => self.say(text),
+            Some(Response::Talk(name)) => {
+                let speaker = match &action.target {
+                    Target::Actor(name) => self.rooms.current().actor_named(name),
+                    Target::Object(_) => None,
+                };
+                self.start_conversation(&name, speaker);
+            }
+            Some(Response::Run(path)) => match self.rooms.current().scripts.get(&path) {
+                Some(program) => self.scripts.push(Script::new(program.clone())),
+                None => eprintln!("{}: script not loaded", path.display()),
+            },
+            Some(Response::PickUp(item)) => {
+                if !self.inventory.add(&item) {
+                    eprintln!("can't pick up unknown item '{}'", item);
+                    return;
+                }
+                if let Target::Object(name) = &action.target {
+                    let room = self.rooms.current_mut();
+                    if let Some(i) = room.object_named(name) {
+                        room.objects.remove(i);
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn start_conversation(&mut self, name: &str, speaker: Option<usize>) {
+        let start = match self.dialogues.get(name) {
+            Some(d) => d.start.clone(),
+            None => {
+                eprintln!("unknown dialogue '{}'", name);
+                return;
+            }
+        };
+        self.conversation = Some(Conversation {
+            dialogue: name.to_owned(),
+            node: start.clone(),
+            speaker,
+        });
+        self.enter_node(start);
+    }
+
+    // the other side says their line, and the conversation is over if that
+    // leaves the player with nothing to answer
+    fn enter_node(&mut self, node: String) {
+        let conversation = match &mut self.conversation {
+            Some(c) => c,
+            None => return,
+        };
+        conversation.node = node;
+        let speaker = conversation.speaker;
+        let text = self.dialogue_node().map(|n| n.text.clone());
+        self.speech = text.map(|t| Speech::new(t, speaker));
+        if self.choices().is_empty() {
+            self.conversation = None;
+        }
+    }
+
+    fn dialogue_node(&self) -> Option<&Node> {
+        let c = self.conversation.as_ref()?;
+        self.dialogues.get(&c.dialogue)?.node(&c.node)
+    }
+
+    fn choices(&self) -> Vec<&Choice> {
+        match self.dialogue_node() {
+            Some(n) => n.choices(&self.flags, &self.inventory).collect(),
+            None => vec![],
+        }
+    }
+
+    fn choose(&mut self, idx: usize) {
+        let choice = match self.choices().get(idx) {
+            Some(&c) => c.clone(),
+            None => return,
+        };
+        for effect in &choice.effects {
+            match effect {
+                Effect::SetFlag(f) => {
+                    self.flags.insert(f.clone());
+                }
+                Effect::GiveItem(i) => {
+                    self.inventory.add(i);
+                }
+                Effect::End => {}
+            }
+        }
+        match choice.next {
+            Some(next) if !choice.ends() => self.enter_node(next),
+            _ => {
+                self.conversation = None;
+                self.say(choice.text);
+            }
+        }
+    }
+
+    fn ui_line(&self, above_bottom: f64) -> f64 {
+        self.screen.h as f64 - above_bottom
+    }
+
+    // the available replies stacked up from the bottom of the screen
+    fn choice_layout(&self) -> Vec<(String, Point, usize)> {
+        let choices = self.choices();
+        let line_height = self.text_writer.make_string(" ").to_bmp().rows() as f64;
+        let top = self.ui_line(VERB_BAR) + line_height - choices.len() as f64 * line_height;
+        choices
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let label = format!(" {}", c.text);
+                let width = self.text_writer.make_string(&label).to_bmp().cols();
+                (
+                    c.text.clone(),
+                    point(10.0, top + i as f64 * line_height),
+                    width,
+                )
+            })
+            .collect()
+    }
+
+    fn choice_at(&self, p: Point) -> Option<usize> {
+        let line_height = self.text_writer.make_string(" ").to_bmp().rows() as f64;
+        self.choice_layout().iter().position(|(_, pos, width)| {
+            p.x >= pos.x && p.x < pos.x + *width as f64 && p.y >= pos.y && p.y < pos.y + line_height
+        })
+    }
+
+    fn draw_choices(&self, buffer: &mut Buffer) {
+        let hovered = self.choice_at(self.mouse_location);
+        for (i, (text, pos, _)) in self.choice_layout().into_iter().enumerate() {
+            let marker = if hovered == Some(i) { '>' } else { ' ' };
+            let bmp = self
+                .text_writer
+                .make_string(&format!("{}{}", marker, text))
+                .to_bmp();
+            buffer.draw_bmp(&bmp, pos);
+        }
+    }
+
+    // the verbs laid out left to right along the bottom of the screen
+    fn verb_layout(&self) -> Vec<(Verb, Point, usize)> {
+        let mut x = 10.0;
+        Verb::ALL
+            .iter()
+            .map(|&v| {
+                let label = format!(" {}", v.label());
+                let width = self.text_writer.make_string(&label).to_bmp().cols();
+                let item = (v, point(x, self.ui_line(VERB_BAR)), width);
+                x += width as f64 + 16.0;
+                item
+            })
+            .collect()
+    }
+
+    fn verb_at(&self, p: Point) -> Option<Verb> {
+        if p.y < self.ui_line(VERB_BAR) {
+            return None;
+        }
+        self.verb_layout()
+            .into_iter()
+            .find(|(_, pos, width)| p.x >= pos.x && p.x < pos.x + *width as f64)
+            .map(|(v, _, _)| v)
+    }
+
+    fn draw_verbs(&self, buffer: &mut Buffer) {
+        let item_name = |name: &str| self.inventory.item(name).map(|i| i.name.as_str());
+        let hovered = self
+            .hovered_target()
+            .or_else(|| item_name(self.inventory.slot_at(self.mouse_location, self.screen)?));
+        let sentence = match (self.held.as_deref().and_then(item_name), hovered) {
+            (Some(held), Some(name)) => format!("Use {} with {}", held, name),
+            (Some(held), None) => format!("Use {} with", held),
+            (None, Some(name)) => format!("{} {}", self.verb.label(), name),
+            (None, None) => self.verb.label().to_owned(),
+        };
+        let bmp = self.text_writer.make_string(&sentence).to_bmp();
+        buffer.draw_bmp(&bmp, point(10.0, self.ui_line(SENTENCE_LINE)));
+
+        for (v, pos, _) in self.verb_layout() {
+            let marker = if v == self.verb { '>' } else { ' ' };
+            let label = format!("{}{}", marker, v.label());
+            let bmp = self.text_writer.make_string(&label).to_bmp();
+            buffer.draw_bmp(&bmp, pos);
+        }
+    }
+
+    // only fires when the character steps onto an exit, so arriving on one
+    // through an entry point doesn't bounce straight back
+    fn check_exits(&mut self, previous_location: Point) {
+        let room = self.rooms.current();
+        if room.exit_at(previous_location).is_some() {
+            return;
+        }
+        if let Some(exit) = room.exit_at(self.character.location) {
+            let (name, entry) = (exit.room.clone(), exit.entry.clone());
+            self.change_room(&name, entry.as_deref());
+        }
+    }
+
+    fn change_room(&mut self, name: &str, entry: Option<&str>) {
+        match self.rooms.enter(name, entry, &mut self.assets) {
+            Ok(spawn) => {
+                self.character.location = spawn;
+                self.character.path.clear();
+                self.character_path = None;
+                self.character_destimation = None;
+                self.mouse_click = false;
+                self.pending = None;
+                self.held = None;
+                self.speech = None;
+                self.conversation = None;
+                self.reset_camera();
+                if let Some(program) = &self.rooms.current().on_enter {
+                    self.scripts.push(Script::new(program.clone()));
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    // straight onto the character in the current room
+    fn reset_camera(&mut self) {
+        self.camera.set_bounds(self.rooms.current().scenery.size());
+        self.camera.center_on(self.character.location);
+    }
+
+    fn skip_cutscene(&mut self) {
+        if let Some(mut cutscene) = self.cutscene.take() {
+            cutscene.skip(self);
+            self.speech = None;
+        }
+    }
+
+    // scripts started while these run get their first go on the next tick
+    fn run_scripts(&mut self, delta: Duration) {
+        let mut scripts = mem::take(&mut self.scripts);
+        scripts.retain_mut(|s| s.resume(self, delta));
+        scripts.append(&mut self.scripts);
+        self.scripts = scripts;
+    }
+
+    // `player` or the name of an actor in the current room
+    fn actor(&self, name: &str) -> Option<&Actor> {
+        if name == "player" {
+            return Some(&self.character);
         }
-        let res = self
-            .walkbox
-            .edges()
-            .map(|side| side.closest_point(self.mouse_location))
-            .fold((f64::MAX, self.mouse_location), |acc, p| {
-                let dist = (p - self.mouse_location).length();
-                if acc.0 > dist {
-                    return (dist, p);
+        let room = self.rooms.current();
+        room.actor_named(name).map(|i| &room.actors[i])
+    }
+    fn actor_mut(&mut self, name: &str) -> Option<&mut Actor> {
+        if name == "player" {
+            return Some(&mut self.character);
+        }
+        let room = self.rooms.current_mut();
+        let i = room.actor_named(name);
+        let actor = i.map(|i| &mut room.actors[i]);
+        if actor.is_none() {
+            eprintln!("no actor named '{}' in this room", name);
+        }
+        actor
+    }
+
+    fn calculate_destination(&self, target: Point) -> Point {
+        self.rooms.current().walkbox.closest_walkable(target)
+    }
+}
+
+impl Host for GameState {
+    fn walk(&mut self, actor: &str, to: Point) -> bool {
+        let to = self.calculate_destination(to);
+        if actor == "player" {
+            self.walk_to(to);
+            return true;
+        }
+        let from = match self.actor_mut(actor) {
+            Some(a) if a.movement_speed.is_some() => a.location,
+            Some(_) => {
+                eprintln!("actor '{}' can't walk", actor);
+                return false;
+            }
+            None => return false,
+        };
+        let path = self.rooms.current_mut().path(from, to);
+        match (path, self.actor_mut(actor)) {
+            (Some(path), Some(a)) => {
+                a.set_path(path.points().map(|p| p.to_owned()));
+                true
+            }
+            _ => false,
+        }
+    }
+    fn place(&mut self, actor: &str, to: Point) {
+        let to = self.calculate_destination(to);
+        if actor == "player" {
+            self.character_path = None;
+        }
+        if let Some(a) = self.actor_mut(actor) {
+            a.location = to;
+            a.path.clear();
+        }
+    }
+    fn is_walking(&self, actor: &str) -> bool {
+        self.actor(actor).is_some_and(|a| !a.path.is_empty())
+    }
+    fn say(&mut self, actor: &str, text: &str) -> bool {
+        let speaker = match actor {
+            "player" => None,
+            _ => match self.rooms.current().actor_named(actor) {
+                Some(i) => Some(i),
+                None => {
+                    eprintln!("no actor named '{}' in this room", actor);
+                    return false;
                 }
-                acc
-            });
-        res.1
+            },
+        };
+        self.speech = Some(Speech::new(text.to_owned(), speaker));
+        true
+    }
+    fn is_talking(&self) -> bool {
+        self.speech.is_some()
+    }
+    fn play(&mut self, actor: &str, image: &Path, control: Control) {
+        let mut image = self.assets.image(image);
+        if let Some(a) = self.actor_mut(actor) {
+            if let Some(animation) = image.animation_mut() {
+                animation.play(control);
+            }
+            a.play(image);
+        }
+    }
+    fn is_animating(&self, actor: &str) -> bool {
+        self.actor(actor)
+            .and_then(|a| a.current_image().animation())
+            .is_some_and(|a| a.is_running())
+    }
+    fn finish_animation(&mut self, actor: &str) {
+        if let Some(a) = self
+            .actor_mut(actor)
+            .and_then(|a| a.current_image_mut().animation_mut())
+        {
+            a.finish();
+        }
+    }
+    fn pause_animation(&mut self, actor: &str, paused: bool) {
+        if let Some(a) = self
+            .actor_mut(actor)
+            .and_then(|a| a.current_image_mut().animation_mut())
+        {
+            match paused {
+                true => a.pause(),
+                false => a.resume(),
+            }
+        }
+    }
+    fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+    fn set_flag(&mut self, name: &str, value: bool) {
+        if value {
+            self.flags.insert(name.to_owned());
+        } else {
+            self.flags.remove(name);
+        }
+    }
+    fn set_walk_area(&mut self, area: &str, enabled: bool) {
+        if !self.rooms.current_mut().set_walk_area(area, enabled) {
+            eprintln!("no walk area named '{}' in this room", area);
+        }
+    }
+    fn change_room(&mut self, room: &str, entry: Option<&str>) {
+        GameState::change_room(self, room, entry);
+    }
+    fn fade(&self) -> f64 {
+        self.fade
+    }
+    fn set_fade(&mut self, level: f64) {
+        self.fade = level;
+    }
+    // the player loses control until it's over
+    fn start_cutscene(&mut self, timeline: &Timeline) {
+        self.pending = None;
+        self.held = None;
+        self.conversation = None;
+        self.verb = Verb::Walk;
+        self.cutscene = Some(Cutscene::new(timeline.clone()));
+    }
+    fn in_cutscene(&self) -> bool {
+        self.cutscene.is_some()
+    }
+}
+
+fn load_inventory(path: &Path, data: &GameData, assets: &mut Assets) -> Result<Inventory, Error> {
+    let mut catalog = HashMap::new();
+    for (id, item) in &data.items {
+        let item = Item {
+            name: item.name.clone(),
+            description: item.description.clone(),
+            icon: assets.load(scene::asset(&item.icon)?)?,
+        };
+        catalog.insert(id.clone(), item);
+    }
+    let mut combinations = vec![];
+    for c in &data.combinations {
+        let (a, b) = c.items.clone();
+        if let Some(unknown) = [&a, &b, &c.result]
+            .into_iter()
+            .find(|n| !catalog.contains_key(*n))
+        {
+            return Err(Error::InvalidScene(
+                path.to_owned(),
+                format!("combination uses unknown item '{}'", unknown),
+            ));
+        }
+        combinations.push((a, b, c.result.clone()));
+    }
+    Ok(Inventory::new(catalog, combinations))
+}
+
+fn load_dialogues(
+    data: &GameData,
+    inventory: &Inventory,
+) -> Result<HashMap<String, Dialogue>, Error> {
+    let mut dialogues = HashMap::new();
+    for (name, path) in &data.dialogues {
+        let dialogue = Dialogue::load(path)?;
+        if let Some(unknown) = dialogue.items().find(|i| inventory.item(i).is_none()) {
+            return Err(Error::InvalidScene(
+                path.clone(),
+                format!("dialogue gives unknown item '{}'", unknown),
+            ));
+        }
+        dialogues.insert(name.clone(), dialogue);
+    }
+    Ok(dialogues)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::Path};
+
+    use winit::event::{ElementState, VirtualKeyCode};
+
+    use crate::{
+        cutscene::Timeline,
+        game::{Depth, Layer, Verb},
+        geometry::{point, rect},
+        image::{Control, Image, Playback},
+        script::Host,
+    };
+
+    use super::{Action, Drawable, GameState, Target, TICK};
+
+    #[test]
+    fn test_save_load() {
+        let mut game_state = GameState::new("resources/game.ron").unwrap();
+        game_state.save_dir = env::temp_dir().join("save_load_test");
+        game_state.rooms.current_mut().objects.clear();
+        game_state.inventory.add("ball");
+        game_state.flags.insert("met_fennec".to_owned());
+        game_state.walk_to(point(300.0, 400.0));
+        let location = game_state.character.location;
+        let path = game_state.character.path.clone();
+        game_state.save(2).unwrap();
+
+        let mut loaded = GameState::new("resources/game.ron").unwrap();
+        loaded.save_dir = game_state.save_dir.clone();
+        assert!(loaded.load(3).is_err());
+        assert!(!loaded.rooms.current().objects.is_empty());
+        loaded.load(2).unwrap();
+        assert!(loaded.rooms.current().objects.is_empty());
+        assert!(loaded.inventory.contains("ball"));
+        assert!(loaded.flags.contains("met_fennec"));
+        assert_eq!(loaded.character.location, location);
+        assert_eq!(loaded.character.path, path);
+    }
+
+    #[test]
+    fn test_conversation() {
+        let mut game_state = GameState::new("resources/game.ron").unwrap();
+        let texts = |g: &GameState| {
+            g.choices()
+                .iter()
+                .map(|c| c.text.clone())
+                .collect::<Vec<_>>()
+        };
+        game_state.start_conversation("fennec", None);
+        assert_eq!(texts(&game_state), vec!["Who are you?", "Bye."]);
+        game_state.choose(0);
+        assert!(game_state.flags.contains("met_fennec"));
+        game_state.choose(0);
+        assert_eq!(texts(&game_state), vec!["Got anything for me?", "Bye."]);
+        game_state.choose(0);
+        game_state.choose(0);
+        assert!(game_state.inventory.contains("bone"));
+        assert_eq!(texts(&game_state), vec!["Bye."]);
+        game_state.choose(0);
+        assert!(game_state.conversation.is_none());
+        assert_eq!(game_state.speech.unwrap().text, "Bye.");
+    }
+
+    #[test]
+    fn test_scripts() {
+        let mut game_state = GameState::new("resources/game.ron").unwrap();
+        game_state.change_room("hall", None);
+        assert_eq!(game_state.scripts.len(), 1);
+        game_state.update(TICK);
+        assert_eq!(game_state.speech.as_ref().unwrap().text, "What a view.");
+        (0..300).for_each(|_| game_state.update(TICK));
+        assert!(game_state.flags.contains("visited_hall"));
+        assert!(game_state.scripts.is_empty());
+
+        let fennec = game_state.rooms.current().actors[0].location;
+        game_state.inventory.add("ball_on_a_stick");
+        game_state.interact(Action {
+            verb: Verb::Use,
+            target: Target::Actor("fennec".to_owned()),
+            item: Some("ball_on_a_stick".to_owned()),
+        });
+        (0..300).for_each(|_| game_state.update(TICK));
+        assert_ne!(game_state.rooms.current().actors[0].location, fennec);
+        (0..1000).for_each(|_| game_state.update(TICK));
+        assert_eq!(game_state.rooms.current().actors[0].location, fennec);
+        assert!(game_state.flags.contains("played_fetch"));
+    }
+
+    #[test]
+    fn test_target_gone() {
+        let mut game_state = GameState::new("resources/game.ron").unwrap();
+        let pick_up = Action {
+            verb: Verb::PickUp,
+            target: Target::Object("ball".to_owned()),
+            item: None,
+        };
+        // gone before the player got there
+        let ball = game_state.rooms.current_mut().objects.remove(0);
+        game_state.interact(pick_up.clone());
+        assert!(!game_state.inventory.contains("ball"));
+
+        game_state.rooms.current_mut().objects.push(ball);
+        game_state.interact(pick_up);
+        assert!(game_state.inventory.contains("ball"));
+        assert!(game_state.rooms.current().objects.is_empty());
+    }
+
+    #[test]
+    fn test_skip_cutscene() {
+        let timeline = Timeline::load("resources/cutscenes/fetch.ron").unwrap();
+        let mut played = GameState::new("resources/game.ron").unwrap();
+        played.change_room("hall", None);
+        played.start_cutscene(&timeline);
+        played.key_pressed(VirtualKeyCode::Key2);
+        assert_eq!(played.verb, Verb::Walk);
+        (0..2000).for_each(|_| played.update(TICK));
+        assert!(played.cutscene.is_none());
+
+        let mut skipped = GameState::new("resources/game.ron").unwrap();
+        skipped.change_room("hall", None);
+        skipped.start_cutscene(&timeline);
+        (0..100).for_each(|_| skipped.update(TICK));
+        assert!(skipped.cutscene.is_some());
+        skipped.key_pressed(VirtualKeyCode::Space);
+        assert!(skipped.cutscene.is_none());
+
+        let fennec = |g: &GameState| g.rooms.current().actors[0].location;
+        assert_eq!(fennec(&skipped), fennec(&played));
+        assert!(skipped.rooms.current().actors[0].path.is_empty());
+        assert_eq!(skipped.fade, played.fade);
+        assert_eq!(skipped.character.location, played.character.location);
+    }
+
+    #[test]
+    fn test_play_once() {
+        let mut game_state = GameState::new("resources/game.ron").unwrap();
+        let control = Control {
+            playback: Playback::HoldLast,
+            ..Control::default()
+        };
+        game_state.play("player", Path::new("resources/fox_walk.png"), control);
+        assert!(game_state.is_animating("player"));
+        game_state.pause_animation("player", true);
+        (0..100).for_each(|_| game_state.update(TICK));
+        assert!(game_state.is_animating("player"));
+        game_state.pause_animation("player", false);
+        (0..100).for_each(|_| game_state.update(TICK));
+        assert!(!game_state.is_animating("player"));
+        let frame = game_state.character.current_image().animation().unwrap();
+        assert_eq!(frame.position().0, 3);
+
+        game_state.play("player", Path::new("resources/fox_walk.png"), control);
+        game_state.finish_animation("player");
+        assert!(!game_state.is_animating("player"));
+    }
+
+    #[test]
+    fn test_reload() {
+        let mut game_state = GameState::new("resources/game.ron").unwrap();
+        game_state.character.location = point(300.0, 380.0);
+        game_state.rooms.current_mut().objects.clear();
+        game_state.inventory.add("ball");
+        game_state.held = Some("ball".to_owned());
+        game_state.pending = Some(Action {
+            verb: Verb::LookAt,
+            target: Target::Actor("fennec".to_owned()),
+            item: None,
+        });
+        game_state.dialogues.clear();
+        let image = game_state.character.image.clone();
+
+        game_state.reload(&[
+            "resources/fox.png".into(),
+            "resources/rooms/start.ron".into(),
+        ]);
+        assert_eq!(game_state.character.location, point(300.0, 380.0));
+        assert!(game_state.rooms.current().objects.is_empty());
+        assert!(game_state.inventory.contains("ball"));
+        assert_eq!((game_state.pending, game_state.held), (None, None));
+        assert!(!game_state.dialogues.is_empty());
+        // the same pixels, read from disk again
+        assert_eq!(game_state.character.image, image);
+        assert_eq!(image.handles(), 1);
+    }
+
+    #[test]
+    fn test_set_screen() {
+        let mut game_state = GameState::new("resources/game.ron").unwrap();
+        assert_eq!(game_state.verb_at(point(20.0, 455.0)), Some(Verb::Walk));
+        game_state.set_screen(rect(320, 240));
+        assert_eq!(game_state.verb_at(point(20.0, 455.0)), Some(Verb::Walk));
+        assert_eq!(game_state.verb_at(point(20.0, 230.0)), Some(Verb::Walk));
+        assert_eq!(game_state.verb_at(point(20.0, 220.0)), None);
+        let camera = game_state.camera.position();
+        assert!(camera.x > 0.0 || camera.y > 0.0, "{:?}", camera);
+    }
+
+    #[test]
+    fn test_camera() {
+        let mut game_state = GameState::new("resources/game.ron").unwrap();
+        // pretend the room is twice as wide as the screen
+        game_state.camera.set_bounds(rect(1280, 465));
+        game_state.character.location = point(600.0, 300.0);
+        (0..200).for_each(|_| game_state.update(TICK));
+        let camera = game_state.camera.position();
+        assert!(camera.x > 150.0 && camera.y == 0.0, "{:?}", camera);
+
+        // clicks land where the mouse points in the room
+        game_state.mouse_over(point(100.0, 300.0));
+        game_state.mouse_click(ElementState::Pressed);
+        game_state.update(TICK);
+        let destination = *game_state.character.path.last().unwrap();
+        assert!((destination.x - (100.0 + camera.x)).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_render_queue() {
+        let mut game_state = GameState::new("resources/game.ron").unwrap();
+        game_state.character.location = point(450.0, 400.0);
+        let queue = game_state.render_queue();
+        assert_eq!(queue, vec![Drawable::Object(0), Drawable::Character]);
+        game_state.character.location = point(450.0, 200.0);
+        let queue = game_state.render_queue();
+        assert_eq!(queue, vec![Drawable::Character, Drawable::Object(0)]);
+        game_state.character.z = Some(1000.0);
+        let queue = game_state.render_queue();
+        assert_eq!(queue, vec![Drawable::Object(0), Drawable::Character]);
+
+        game_state.change_room("hall", None);
+        game_state.character.z = None;
+        game_state.character.location = point(524.0, 380.0);
+        let queue = game_state.render_queue();
+        assert_eq!(queue.last(), Some(&Drawable::Mask(0)));
+
+        let layer = Layer::new(
+            Image::load("resources/stick.png").unwrap(),
+            point(0.0, 0.0),
+            1.2,
+            Depth::Baseline(2000.0),
+        );
+        game_state.rooms.current_mut().scenery.layers.push(layer);
+        let queue = game_state.render_queue();
+        assert_eq!(queue.last(), Some(&Drawable::Layer(0)));
     }
 }
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use crate::game::{astar, ShortestPath, WalkBox};
 
 use super::{line::line_segment, LineSegment, Point};
@@ -5,28 +7,74 @@ use super::{line::line_segment, LineSegment, Point};
 #[derive(Default, Debug, PartialEq, Clone)]
 pub struct Graph {
     walkbox: WalkBox,
+    nodes: Vec<Point>,
     pub walkable_edges: Vec<LineSegment>,
     pub temp_edges: Vec<LineSegment>,
 }
 impl Graph {
     pub fn new(wb: WalkBox) -> Self {
+        let nodes = wb.concave_vertexes();
         let mut edges: Vec<LineSegment> = vec![];
-        wb.concave_vertexes().for_each(|i| {
-            wb.concave_vertexes().for_each(|j| {
+        for &i in &nodes {
+            for &j in &nodes {
                 let ls = line_segment(i, j);
-                if ls.length() >= f64::EPSILON && !wb.intersects(&ls) {
-                    if !edges.iter().any(|e| e.start == ls.start && e.end == ls.end) {
-                        edges.push(ls);
-                    }
+                if ls.length() >= f64::EPSILON && wb.sees(i, j) {
+                    edges.push(ls);
                 }
-            })
-        });
+            }
+        }
         Self {
             walkbox: wb,
+            nodes,
             walkable_edges: edges,
             temp_edges: vec![],
         }
     }
+    // switches a walk area on or off and updates only the edges that could
+    // have changed: switching one on can only add corners and edges, and only
+    // edges passing over the area, switching one off can only take them away.
+    // false if there's no area by that name
+    pub fn set_enabled(&mut self, area: &str, enabled: bool) -> bool {
+        let Some((min, max)) = self.walkbox.set_enabled(area, enabled) else {
+            return false;
+        };
+        let over = |l: &LineSegment| {
+            l.start.x.max(l.end.x) >= min.x
+                && l.start.x.min(l.end.x) <= max.x
+                && l.start.y.max(l.end.y) >= min.y
+                && l.start.y.min(l.end.y) <= max.y
+        };
+        let nodes = self.walkbox.concave_vertexes();
+        if enabled {
+            let old: HashSet<Point> = self.nodes.iter().copied().collect();
+            let mut edges: HashSet<LineSegment> = self.walkable_edges.iter().copied().collect();
+            for &i in &nodes {
+                for &j in &nodes {
+                    let ls = line_segment(i, j);
+                    let new = !old.contains(&i) || !old.contains(&j);
+                    if ls.length() >= f64::EPSILON
+                        && (new || over(&ls))
+                        && !edges.contains(&ls)
+                        && self.walkbox.sees(i, j)
+                    {
+                        edges.insert(ls);
+                        self.walkable_edges.push(ls);
+                    }
+                }
+            }
+        } else {
+            let kept: HashSet<Point> = nodes.iter().copied().collect();
+            let walkbox = &self.walkbox;
+            self.walkable_edges.retain(|l| {
+                kept.contains(&l.start)
+                    && kept.contains(&l.end)
+                    && (!over(l) || walkbox.sees(l.start, l.end))
+            });
+        }
+        self.nodes = nodes;
+        self.temp_edges.clear();
+        true
+    }
     pub fn walkable_edges(&self) -> impl Iterator<Item = &LineSegment> + '_ {
         self.walkable_edges.iter().chain(self.temp_edges.iter())
     }
@@ -35,11 +83,11 @@ impl Graph {
         if let Some(l) = self.add_edge(location, pointer) {
             self.temp_edges.push(l);
         }
-        for end in self.walkbox.concave_vertexes() {
+        for &end in &self.nodes {
             if let Some(l) = self.add_edge(location, end) {
                 self.temp_edges.push(l);
             }
-            if self.walkbox.contains(pointer) {
+            if self.walkbox.walkable(pointer) {
                 if let Some(l) = self.add_edge(end, pointer) {
                     self.temp_edges.push(l);
                 }
@@ -51,7 +99,7 @@ impl Graph {
         if l.length() < f64::EPSILON {
             return None;
         }
-        if !self.walkbox.intersects(&l) {
+        if self.walkbox.sees(start, end) {
             if !self
                 .temp_edges
                 .iter()
@@ -103,6 +151,85 @@ mod tests {
         ))
     }
 
+    #[test]
+    fn test_around_obstacle() {
+        let mut graph = Graph::new(WalkBox::new(
+            Polygon::new(vec![
+                point(0.0, 0.0),
+                point(100.0, 0.0),
+                point(100.0, 100.0),
+                point(0.0, 100.0),
+            ]),
+            vec![Polygon::new(vec![
+                point(40.0, 30.0),
+                point(60.0, 30.0),
+                point(60.0, 70.0),
+                point(40.0, 70.0),
+            ])],
+        ));
+        // no shortcut across the table between opposite corners
+        assert!(!graph
+            .walkable_edges()
+            .any(|e| e.start == point(40.0, 30.0) && e.end == point(60.0, 70.0)));
+
+        let start = point(20.0, 50.0);
+        let end = point(80.0, 50.0);
+        graph.add_temporary_edges(start, end);
+        let path = graph.path_to(start, end).unwrap();
+        let points: Vec<_> = path.points().copied().collect();
+        assert_eq!(points.len(), 4);
+        assert!(path.lines().all(|l| graph.walkbox.sees(l.start, l.end)));
+        assert_eq!(points[1].x, 40.0);
+        assert_eq!(points[2].x, 60.0);
+        assert_eq!(points[1].y, points[2].y);
+    }
+
+    #[test]
+    fn test_set_enabled() {
+        let square = |x0: f64, y0: f64, x1: f64, y1: f64| {
+            Polygon::new(vec![
+                point(x0, y0),
+                point(x1, y0),
+                point(x1, y1),
+                point(x0, y1),
+            ])
+        };
+        let mut walkbox = WalkBox::new(
+            square(0.0, 0.0, 100.0, 100.0),
+            vec![square(40.0, 30.0, 60.0, 70.0)],
+        );
+        walkbox.add_area("bridge", square(100.0, 40.0, 200.0, 60.0), vec![], false);
+        walkbox.add_area("ledge", square(20.0, 100.0, 60.0, 140.0), vec![], true);
+        let sorted = |graph: &Graph| {
+            let mut edges = graph.walkable_edges.clone();
+            edges.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            edges
+        };
+
+        let mut graph = Graph::new(walkbox.clone());
+        let start = point(20.0, 50.0);
+        let end = point(190.0, 50.0);
+        graph.add_temporary_edges(start, end);
+        assert!(graph.path_to(start, end).is_none());
+
+        // the same graph as building it from scratch
+        let before = sorted(&graph);
+        assert!(graph.set_enabled("bridge", true));
+        walkbox.set_enabled("bridge", true);
+        assert_eq!(sorted(&graph), sorted(&Graph::new(walkbox.clone())));
+        graph.add_temporary_edges(start, end);
+        assert!(graph.path_to(start, end).is_some());
+
+        assert!(graph.set_enabled("ledge", false));
+        walkbox.set_enabled("ledge", false);
+        assert_eq!(sorted(&graph), sorted(&Graph::new(walkbox.clone())));
+
+        graph.set_enabled("bridge", false);
+        graph.set_enabled("ledge", true);
+        assert_eq!(sorted(&graph), before);
+        assert!(!graph.set_enabled("gate", true));
+    }
+
     #[test]
     fn test_walkable_edges() {
         let graph = make_graph();
@@ -1,12 +1,14 @@
 mod graph;
 mod line;
+mod navmesh;
 mod point;
 mod polygon;
 mod rect;
 mod vector;
 
 pub use graph::Graph;
-pub use line::{line, line_segment, Line, LineSegment, LineType};
+pub use line::{line_segment, LineSegment, LineType};
+pub use navmesh::NavMesh;
 pub use point::{point, Point};
 pub use polygon::Polygon;
 pub use rect::{rect, Rect};
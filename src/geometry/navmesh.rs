@@ -0,0 +1,648 @@
+// pathfinding over a triangulated walkbox: A* finds a run of triangles from
+// the start to the end, going between the middles of the edges crossed, and
+// the funnel algorithm pulls a string tight through them; without comparing
+// every corner against every other, at the price of the odd path a little
+// longer than the visibility graph's
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::game::{ShortestPath, WalkBox};
+
+use super::{line_segment, LineSegment, Point, Polygon};
+
+// how far outside every triangle a point can be and still be walked from
+const SNAP_DISTANCE: f64 = 0.5;
+const EPSILON: f64 = 1e-6;
+
+#[derive(Debug, PartialEq, Clone)]
+struct Triangle {
+    corners: [Point; 3],
+    // the neighbour and the part of the edge shared with it
+    portals: Vec<(usize, Point, Point)>,
+}
+impl Triangle {
+    fn centre(&self) -> Point {
+        let [a, b, c] = self.corners;
+        a + ((b - a) + (c - a)) * (1.0 / 3.0)
+    }
+    fn edges(&self) -> impl Iterator<Item = LineSegment> + '_ {
+        (0..3).map(|i| line_segment(self.corners[i], self.corners[(i + 1) % 3]))
+    }
+    // zero inside
+    fn distance(&self, p: Point) -> f64 {
+        let [a, b, c] = self.corners;
+        let sides = [area2(a, b, p), area2(b, c, p), area2(c, a, p)];
+        if sides.iter().all(|s| *s >= 0.0) || sides.iter().all(|s| *s <= 0.0) {
+            return 0.0;
+        }
+        self.edges()
+            .map(|e| (e.closest_point(p) - p).length())
+            .fold(f64::MAX, f64::min)
+    }
+}
+
+// every area is cut up once, switching one only joins the triangles again
+#[derive(Debug, PartialEq, Clone)]
+struct Area {
+    name: String,
+    enabled: bool,
+    triangles: Vec<[Point; 3]>,
+}
+
+#[derive(Default, Debug, PartialEq, Clone)]
+pub struct NavMesh {
+    areas: Vec<Area>,
+    triangles: Vec<Triangle>,
+}
+impl NavMesh {
+    // areas are joined where their edges run along each other, ones that only
+    // overlap aren't
+    pub fn new(walkbox: &WalkBox) -> Result<Self, String> {
+        let areas = walkbox
+            .polygons()
+            .map(|(name, enabled, exterior, interior)| {
+                Ok(Area {
+                    name: name.to_owned(),
+                    enabled,
+                    triangles: triangulate(exterior, interior)?,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        let mut mesh = Self {
+            areas,
+            triangles: vec![],
+        };
+        mesh.join();
+        Ok(mesh)
+    }
+    // false if there's no area by that name
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let Some(area) = self
+            .areas
+            .iter_mut()
+            .find(|a| !name.is_empty() && a.name == name)
+        else {
+            return false;
+        };
+        area.enabled = enabled;
+        self.join();
+        true
+    }
+    // triangles sharing a whole edge meet it going opposite ways round, only
+    // the edges left over can meet part of another area's
+    fn join(&mut self) {
+        self.triangles = self
+            .areas
+            .iter()
+            .filter(|a| a.enabled)
+            .flat_map(|a| &a.triangles)
+            .map(|corners| Triangle {
+                corners: *corners,
+                portals: vec![],
+            })
+            .collect();
+        let mut edges: HashMap<(Point, Point), usize> = HashMap::new();
+        for i in 0..self.triangles.len() {
+            for e in self.triangles[i].edges().collect::<Vec<_>>() {
+                if let Some(j) = edges.remove(&(e.end, e.start)) {
+                    self.triangles[i].portals.push((j, e.start, e.end));
+                    self.triangles[j].portals.push((i, e.start, e.end));
+                } else {
+                    edges.insert((e.start, e.end), i);
+                }
+            }
+        }
+        let mut left: Vec<_> = edges.into_iter().collect();
+        left.sort_by_key(|(e, i)| (*i, *e));
+        for (n, ((a, b), i)) in left.iter().enumerate() {
+            for ((c, d), j) in &left[n + 1..] {
+                if i == j || self.triangles[*i].portals.iter().any(|p| p.0 == *j) {
+                    continue;
+                }
+                if let Some((x, y)) = overlap(&line_segment(*a, *b), &line_segment(*c, *d)) {
+                    self.triangles[*i].portals.push((*j, x, y));
+                    self.triangles[*j].portals.push((*i, x, y));
+                }
+            }
+        }
+    }
+    pub fn edges(&self) -> impl Iterator<Item = LineSegment> + '_ {
+        self.triangles.iter().flat_map(Triangle::edges)
+    }
+    // A* with a node per triangle, each entered at the middle of the edge
+    // crossed into it
+    pub fn path_to(&self, start: Point, end: Point) -> Option<ShortestPath> {
+        let from = self.triangle_at(start)?;
+        let to = self.triangle_at(end)?;
+        let mut frontier = BinaryHeap::new();
+        // the cost so far and where each triangle was entered
+        let mut distances: HashMap<usize, (f64, Point)> = HashMap::from([(from, (0.0, start))]);
+        // the triangle each was entered from and the edge crossed
+        let mut came_from: HashMap<usize, (usize, Point, Point)> = HashMap::new();
+        frontier.push(State {
+            triangle: from,
+            cost: 0.0,
+        });
+        while let Some(State {
+            triangle: current, ..
+        }) = frontier.pop()
+        {
+            if current == to {
+                break;
+            }
+            let (cost, entry) = distances[&current];
+            for &(next, a, b) in &self.triangles[current].portals {
+                let middle = a + (b - a) * 0.5;
+                let next_cost = cost + (middle - entry).length();
+                if distances.get(&next).is_none_or(|(c, _)| next_cost < *c) {
+                    distances.insert(next, (next_cost, middle));
+                    came_from.insert(next, (current, a, b));
+                    frontier.push(State {
+                        triangle: next,
+                        cost: next_cost + (end - middle).length(),
+                    });
+                }
+            }
+        }
+        if !distances.contains_key(&to) {
+            return None;
+        }
+
+        // the edges crossed, left and right as seen going through
+        let mut portals = vec![(end, end)];
+        let mut current = to;
+        while let Some(&(previous, a, b)) = came_from.get(&current) {
+            let centre = self.triangles[previous].centre();
+            portals.push(if area2(centre, a, b) < 0.0 {
+                (a, b)
+            } else {
+                (b, a)
+            });
+            current = previous;
+        }
+        portals.push((start, start));
+        portals.reverse();
+        let mut points = funnel(&portals);
+        if points.last() != Some(&end) {
+            points.push(end);
+        }
+        Some(ShortestPath::through(points))
+    }
+    fn triangle_at(&self, p: Point) -> Option<usize> {
+        self.triangles
+            .iter()
+            .map(|t| t.distance(p))
+            .enumerate()
+            .filter(|(_, d)| *d <= SNAP_DISTANCE)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(i, _)| i)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct State {
+    triangle: usize,
+    cost: f64,
+}
+impl Eq for State {}
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| self.triangle.cmp(&other.triangle))
+    }
+}
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// twice the signed area of the triangle, which side of `a`-`b` `c` is on
+fn area2(a: Point, b: Point, c: Point) -> f64 {
+    (b - a).cross(c - a)
+}
+
+// the part two edges have in common, if they run along the same line
+fn overlap(e: &LineSegment, f: &LineSegment) -> Option<(Point, Point)> {
+    let d = e.end - e.start;
+    let length = d.length();
+    if length < EPSILON
+        || area2(e.start, e.end, f.start).abs() > EPSILON * length
+        || area2(e.start, e.end, f.end).abs() > EPSILON * length
+    {
+        return None;
+    }
+    let t = |p: Point| {
+        let v = p - e.start;
+        (v.x * d.x + v.y * d.y) / (length * length)
+    };
+    let (t0, t1) = (t(f.start), t(f.end));
+    let (t0, t1) = (t0.min(t1).max(0.0), t0.max(t1).min(1.0));
+    ((t1 - t0) * length > EPSILON).then(|| (e.start + d * t0, e.start + d * t1))
+}
+
+// the simple stupid funnel algorithm: the path is pulled tight through the
+// portals, bending only where one side of the funnel crosses the other; the
+// start and the corners bent round
+fn funnel(portals: &[(Point, Point)]) -> Vec<Point> {
+    let mut points = vec![portals[0].0];
+    let (mut apex, mut left, mut right) = (portals[0].0, portals[0].0, portals[0].1);
+    let (mut left_index, mut right_index) = (0, 0);
+    let mut i = 1;
+    while i < portals.len() {
+        let (mut l, mut r) = portals[i];
+        // a portal in line with the apex either runs through it, which
+        // leaves the funnel as it is, or the path reaches it at its near end
+        if l != r && area2(apex, l, r).abs() <= EPSILON * (r - l).length() {
+            if (l - apex).dot(r - apex) <= 0.0 {
+                i += 1;
+                continue;
+            }
+            if (l - apex).length() < (r - apex).length() {
+                r = l;
+            } else {
+                l = r;
+            }
+        }
+        let mut bend = None;
+        if area2(apex, right, r) >= 0.0 && !behind(apex, right, r) {
+            if apex == right || area2(apex, left, r) < 0.0 {
+                (right, right_index) = (r, i);
+            } else {
+                bend = Some((left, left_index));
+            }
+        }
+        if bend.is_none() && area2(apex, left, l) <= 0.0 && !behind(apex, left, l) {
+            if apex == left || area2(apex, right, l) > 0.0 {
+                (left, left_index) = (l, i);
+            } else {
+                bend = Some((right, right_index));
+            }
+        }
+        // one side crossed the other: the path bends around the corner it
+        // crossed and the funnel starts again from there
+        if let Some((corner, index)) = bend {
+            points.push(corner);
+            apex = corner;
+            (left, left_index) = (corner, index);
+            (right, right_index) = (corner, index);
+            i = index;
+        }
+        i += 1;
+    }
+    points
+}
+
+// in line with the apex and past the side of the funnel, so it narrows the
+// funnel no further; the nearer corner is the one the path bends round
+fn behind(apex: Point, side: Point, p: Point) -> bool {
+    apex != side
+        && area2(apex, side, p).abs() <= EPSILON * (p - apex).length()
+        && (p - apex).length() > (side - apex).length()
+}
+
+// ear clipping, after joining each obstacle to the outside by a cut so the
+// whole thing is one polygon; fails on obstacles that can't be joined and
+// outlines that cross themselves, rather than leaving floor out
+fn triangulate(exterior: &Polygon, interior: &[Polygon]) -> Result<Vec<[Point; 3]>, String> {
+    let mut outline = exterior.vertices.clone();
+    outline.dedup();
+    if outline.len() > 1 && outline.first() == outline.last() {
+        outline.pop();
+    }
+    let mut holes: Vec<Vec<Point>> = interior
+        .iter()
+        .map(|h| h.vertices.iter().rev().copied().collect())
+        .collect();
+    let rightmost = |h: &Vec<Point>| h.iter().map(|p| p.x).fold(f64::MIN, f64::max);
+    holes.sort_by(|a, b| rightmost(b).partial_cmp(&rightmost(a)).unwrap());
+    for (n, hole) in holes.iter().enumerate() {
+        let m = (0..hole.len())
+            .max_by(|a, b| hole[*a].x.partial_cmp(&hole[*b].x).unwrap())
+            .unwrap_or_default();
+        let blocked = |p: Point| {
+            let cut = line_segment(hole[m], p);
+            let middle = hole[m] + (p - hole[m]) * 0.5;
+            ring_edges(&outline)
+                .chain(holes[n..].iter().flat_map(|h| ring_edges(h)))
+                .any(|e| cut.crossing(&e).is_some())
+                || !exterior.contains(middle)
+                || interior.iter().any(|h| h.contains(middle))
+        };
+        let mut candidates: Vec<usize> = (0..outline.len()).collect();
+        candidates.sort_by(|a, b| {
+            let da = (outline[*a] - hole[m]).length();
+            let db = (outline[*b] - hole[m]).length();
+            da.partial_cmp(&db).unwrap()
+        });
+        let Some(p) = candidates.into_iter().find(|i| !blocked(outline[*i])) else {
+            return Err(format!(
+                "obstacle at ({}, {}) can't be joined to the walkbox's edge",
+                hole[m].x, hole[m].y
+            ));
+        };
+        let mut joined = outline[..=p].to_vec();
+        joined.extend(hole[m..].iter().chain(&hole[..=m]));
+        joined.extend(&outline[p..]);
+        outline = joined;
+    }
+
+    let mut triangles = vec![];
+    while outline.len() > 3 {
+        let n = outline.len();
+        let corner = |i: usize| [outline[(i + n - 1) % n], outline[i], outline[(i + 1) % n]];
+        // straight or doubled back corners go first, they add nothing
+        if let Some(i) = (0..n).find(|i| {
+            let [a, b, c] = corner(*i);
+            area2(a, b, c).abs() < EPSILON
+        }) {
+            outline.remove(i);
+            continue;
+        }
+        let ear = (0..n).find(|i| {
+            let [a, b, c] = corner(*i);
+            area2(a, b, c) > 0.0
+                && !outline.iter().any(|p| {
+                    *p != a
+                        && *p != b
+                        && *p != c
+                        && area2(a, b, *p) >= 0.0
+                        && area2(b, c, *p) >= 0.0
+                        && area2(c, a, *p) >= 0.0
+                })
+        });
+        let Some(i) = ear else {
+            return Err(format!(
+                "walkbox can't be cut into triangles around ({}, {}), do its edges cross?",
+                outline[0].x, outline[0].y
+            ));
+        };
+        triangles.push(corner(i));
+        outline.remove(i);
+    }
+    if outline.len() == 3 && area2(outline[0], outline[1], outline[2]).abs() >= EPSILON {
+        triangles.push([outline[0], outline[1], outline[2]]);
+    }
+    flip(&mut triangles);
+    Ok(triangles)
+}
+
+// ear clipping leaves long thin slivers that make paths awkward to search,
+// flipping the edge between two triangles whenever one has the other's far
+// corner inside its circumcircle evens them out; only the edges around a
+// flipped one need looking at again
+fn flip(triangles: &mut [[Point; 3]]) {
+    let key = |a: Point, b: Point| (a.min(b), a.max(b));
+    let mut owners: HashMap<(Point, Point), Vec<usize>> = HashMap::new();
+    let mut stack = vec![];
+    for (i, t) in triangles.iter().enumerate() {
+        for k in 0..3 {
+            let e = key(t[k], t[(k + 1) % 3]);
+            owners.entry(e).or_default().push(i);
+            stack.push(e);
+        }
+    }
+    stack.reverse();
+    while let Some(e) = stack.pop() {
+        let Some(&[i, j]) = owners.get(&e).map(Vec::as_slice) else {
+            continue;
+        };
+        let (t, u) = (triangles[i], triangles[j]);
+        let Some((x, y, p, q)) = (0..3).find_map(|k| {
+            let (x, y, p) = (t[k], t[(k + 1) % 3], t[(k + 2) % 3]);
+            (0..3)
+                .find(|m| u[*m] == y && u[(m + 1) % 3] == x)
+                .map(|m| (x, y, p, u[(m + 2) % 3]))
+        }) else {
+            continue;
+        };
+        if area2(x, q, p) <= EPSILON || area2(q, y, p) <= EPSILON || !in_circle(t, q) {
+            continue;
+        }
+        owners.remove(&e);
+        owners.get_mut(&key(y, p)).unwrap().retain(|o| *o != i);
+        owners.get_mut(&key(x, q)).unwrap().retain(|o| *o != j);
+        triangles[i] = [x, q, p];
+        triangles[j] = [q, y, p];
+        owners.entry(key(y, p)).or_default().push(j);
+        owners.entry(key(x, q)).or_default().push(i);
+        owners.insert(key(p, q), vec![i, j]);
+        stack.extend([key(x, q), key(q, y), key(y, p), key(p, x)]);
+    }
+}
+
+fn in_circle([a, b, c]: [Point; 3], d: Point) -> bool {
+    let (a, b, c) = (a - d, b - d, c - d);
+    a.length_sq() * b.cross(c) + b.length_sq() * c.cross(a) + c.length_sq() * a.cross(b) > EPSILON
+}
+
+fn ring_edges(ring: &[Point]) -> impl Iterator<Item = LineSegment> + '_ {
+    (0..ring.len()).map(move |i| line_segment(ring[i], ring[(i + 1) % ring.len()]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use crate::{
+        game::{ShortestPath, WalkBox},
+        geometry::{point, Graph, Point, Polygon},
+    };
+
+    use super::{area2, triangulate, NavMesh};
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Polygon {
+        Polygon::new(vec![
+            point(x0, y0),
+            point(x1, y0),
+            point(x1, y1),
+            point(x0, y1),
+        ])
+    }
+
+    // the start room's floor with a table in it
+    fn walkbox() -> WalkBox {
+        WalkBox::new(
+            Polygon::new(vec![
+                point(60.0, 60.0),
+                point(300.0, 60.0),
+                point(300.0, 240.0),
+                point(360.0, 240.0),
+                point(360.0, 60.0),
+                point(610.0, 60.0),
+                point(610.0, 260.0),
+                point(510.0, 260.0),
+                point(510.0, 280.0),
+                point(610.0, 280.0),
+                point(610.0, 435.0),
+                point(60.0, 435.0),
+                point(60.0, 60.0),
+            ]),
+            vec![rect(150.0, 300.0, 250.0, 350.0)],
+        )
+    }
+
+    fn length(path: &ShortestPath) -> f64 {
+        path.lines().map(|l| l.length()).sum()
+    }
+
+    fn graph_path(walkbox: &WalkBox, start: Point, end: Point) -> Option<ShortestPath> {
+        let mut graph = Graph::new(walkbox.clone());
+        graph.add_temporary_edges(start, end);
+        graph.path_to(start, end)
+    }
+
+    #[test]
+    fn test_triangulate() {
+        let triangles = triangulate(
+            &rect(0.0, 0.0, 100.0, 100.0),
+            &[rect(40.0, 30.0, 60.0, 70.0)],
+        )
+        .unwrap();
+        assert_eq!(triangles.len(), 8);
+        let area: f64 = triangles
+            .iter()
+            .map(|[a, b, c]| area2(*a, *b, *c) / 2.0)
+            .sum();
+        assert_eq!(area, 100.0 * 100.0 - 20.0 * 40.0);
+        assert!(triangles.iter().all(|[a, b, c]| area2(*a, *b, *c) > 0.0));
+
+        // an obstacle sticking out of the floor, and a floor crossing itself
+        assert!(triangulate(
+            &rect(0.0, 0.0, 100.0, 100.0),
+            &[rect(40.0, 30.0, 160.0, 70.0)]
+        )
+        .is_err());
+        let bow_tie = Polygon::new(vec![
+            point(0.0, 0.0),
+            point(100.0, 100.0),
+            point(100.0, 0.0),
+            point(0.0, 100.0),
+            point(50.0, 120.0),
+        ]);
+        assert!(triangulate(&bow_tie, &[]).is_err());
+    }
+
+    #[test]
+    fn test_paths_like_graph() {
+        let walkbox = walkbox();
+        let mesh = NavMesh::new(&walkbox).unwrap();
+        for (start, end) in [
+            (point(150.0, 150.0), point(570.0, 120.0)),
+            (point(600.0, 290.0), point(600.0, 190.0)),
+            (point(100.0, 325.0), point(300.0, 325.0)),
+            (point(200.0, 400.0), point(200.0, 100.0)),
+            (point(80.0, 80.0), point(90.0, 90.0)),
+            // from a corner and from an edge of the table
+            (point(250.0, 300.0), point(100.0, 400.0)),
+            (point(200.0, 350.0), point(570.0, 120.0)),
+        ] {
+            // never shorter than the shortest, and not by much longer here
+            let expected = graph_path(&walkbox, start, end).unwrap();
+            let path = mesh.path_to(start, end).unwrap();
+            let (m, g) = (length(&path), length(&expected));
+            assert!(
+                m > g - 1e-6 && m < g * 1.05,
+                "{:?} to {:?}: {:?}, expected {:?}",
+                start,
+                end,
+                path,
+                expected
+            );
+            assert!(path.lines().all(|l| walkbox.sees(l.start, l.end)));
+        }
+        // into the table or out of the room
+        assert!(mesh
+            .path_to(point(100.0, 100.0), point(200.0, 320.0))
+            .is_none());
+        assert!(mesh
+            .path_to(point(100.0, 100.0), point(330.0, 100.0))
+            .is_none());
+    }
+
+    #[test]
+    fn test_areas() {
+        let mut walkbox = WalkBox::new(rect(0.0, 0.0, 100.0, 100.0), vec![]);
+        walkbox.add_area("bridge", rect(100.0, 40.0, 200.0, 60.0), vec![], false);
+        let (start, end) = (point(10.0, 10.0), point(190.0, 50.0));
+        let mut mesh = NavMesh::new(&walkbox).unwrap();
+        assert!(mesh.path_to(start, end).is_none());
+
+        assert!(mesh.set_enabled("bridge", true));
+        assert!(!mesh.set_enabled("pier", true));
+        let path = mesh.path_to(start, end).unwrap();
+        let points: Vec<_> = path.points().copied().collect();
+        assert_eq!(points, vec![start, point(100.0, 40.0), end]);
+    }
+
+    // cargo test --release bench_navmesh -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_navmesh() {
+        // a hall full of pillars
+        let pillars = (0..6)
+            .flat_map(|row| (0..8).map(move |column| (row, column)))
+            .map(|(row, column)| {
+                let (x, y) = (60.0 + column as f64 * 70.0, 60.0 + row as f64 * 60.0);
+                rect(x, y, x + 20.0 + (row % 3) as f64 * 5.0, y + 20.0)
+            })
+            .collect();
+        let walkbox = WalkBox::new(rect(0.0, 0.0, 640.0, 420.0), pillars);
+        let queries: Vec<_> = (0..50)
+            .map(|i| {
+                let y = 10.0 + 8.0 * i as f64;
+                (point(10.0, y), point(630.0, 420.0 - y))
+            })
+            .collect();
+
+        let time = Instant::now();
+        let mut graph = Graph::new(walkbox.clone());
+        let built = time.elapsed();
+        let time = Instant::now();
+        let graph_lengths: Vec<_> = queries
+            .iter()
+            .map(|(start, end)| {
+                graph.add_temporary_edges(*start, *end);
+                length(&graph.path_to(*start, *end).unwrap())
+            })
+            .collect();
+        println!(
+            "graph:   built in {:?}, 50 paths in {:?}",
+            built,
+            time.elapsed()
+        );
+
+        let time = Instant::now();
+        let mesh = NavMesh::new(&walkbox).unwrap();
+        let built = time.elapsed();
+        let time = Instant::now();
+        let mesh_lengths: Vec<_> = queries
+            .iter()
+            .map(|(start, end)| length(&mesh.path_to(*start, *end).unwrap()))
+            .collect();
+        println!(
+            "navmesh: built in {:?}, 50 paths in {:?}",
+            built,
+            time.elapsed()
+        );
+
+        let mut longest = 1.0f64;
+        for ((start, end), (g, m)) in queries.iter().zip(graph_lengths.iter().zip(&mesh_lengths)) {
+            assert!(
+                *m > g - 1e-6,
+                "{:?} to {:?}: {}, shorter than {}",
+                start,
+                end,
+                m,
+                g
+            );
+            longest = longest.max(m / g);
+        }
+        println!("navmesh: paths up to {:.2} times as long", longest);
+    }
+}
@@ -1,4 +1,7 @@
-use std::ops::{Add, Mul, Sub};
+use std::{
+    hash::{Hash, Hasher},
+    ops::{Add, Mul, Sub},
+};
 
 use winit::dpi::PhysicalPosition;
 
@@ -10,10 +13,6 @@ pub struct Point {
     pub y: f64,
 }
 impl Point {
-    #[inline(always)]
-    pub fn xy(&self) -> (usize, usize) {
-        (self.x as usize, self.y as usize)
-    }
     #[inline(always)]
     pub fn is_convex(p: Self, c: Self, n: Self) -> bool {
         let left = c - p;
@@ -93,6 +92,11 @@ impl From<PhysicalPosition<f64>> for Point {
         Self { x: p.x, y: p.y }
     }
 }
+impl From<(f64, f64)> for Point {
+    fn from(p: (f64, f64)) -> Self {
+        Self { x: p.0, y: p.1 }
+    }
+}
 impl From<(usize, usize)> for Point {
     fn from(p: (usize, usize)) -> Self {
         Self {
@@ -103,6 +107,13 @@ impl From<(usize, usize)> for Point {
 }
 
 impl Eq for Point {}
+// -0.0 is equal to 0.0 so it has to hash the same
+impl Hash for Point {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.x + 0.0).to_bits().hash(state);
+        (self.y + 0.0).to_bits().hash(state);
+    }
+}
 impl Ord for Point {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.x
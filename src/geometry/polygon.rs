@@ -17,36 +17,49 @@ impl Polygon {
             .map(|w| line_segment(w[0], w[1]))
             .chain(iter::once(l))
     }
+    // every vertex with the ones either side of it, wrapping around
+    fn corners(&self) -> impl Iterator<Item = [Point; 3]> + '_ {
+        let n = self.vertices.len();
+        (0..n).map(move |i| {
+            [
+                self.vertices[(i + n - 1) % n],
+                self.vertices[i],
+                self.vertices[(i + 1) % n],
+            ]
+        })
+    }
+    // both these assume the vertices go clockwise on screen
     pub fn concave_vertices(&self) -> impl Iterator<Item = Point> + '_ {
-        let v = self.vertices.windows(3).filter_map(|pp| {
-            if Point::is_convex(pp[0], pp[1], pp[2]) {
-                Some(pp[1])
-            } else {
-                None
-            }
-        });
-        let o = iter::once([
-            self.vertices[self.vertices.len() - 1],
-            self.vertices[0],
-            self.vertices[1],
-        ])
-        .filter_map(|pp| {
-            if Point::is_convex(pp[0], pp[1], pp[2]) {
-                Some(self.vertices[0])
-            } else {
-                None
+        self.corners()
+            .filter(|[p, c, n]| Point::is_convex(*p, *c, *n))
+            .map(|[_, c, _]| c)
+    }
+    pub fn contains(&self, p: Point) -> bool {
+        let mut inside = false;
+        for e in self.edges() {
+            let (a, b) = (e.start, e.end);
+            if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+                inside = !inside;
             }
-        });
-        v.chain(o)
+        }
+        inside
     }
     pub fn convex_vertices(&self) -> impl Iterator<Item = Point> + '_ {
-        self.vertices.windows(3).filter_map(|pp| {
-            if !Point::is_convex(pp[0], pp[1], pp[2]) {
-                Some(pp[1])
-            } else {
-                None
-            }
-        })
+        self.corners()
+            .filter(|[p, c, n]| !Point::is_convex(*p, *c, *n) && (*c - *p).cross(*n - *c) != 0.0)
+            .map(|[_, c, _]| c)
+    }
+    // with y pointing down, by the sign of the shoelace area
+    pub fn is_clockwise(&self) -> bool {
+        let area: f64 = self
+            .edges()
+            .map(|e| e.start.x * e.end.y - e.end.x * e.start.y)
+            .sum();
+        area > 0.0
+    }
+    pub fn reversed(mut self) -> Self {
+        self.vertices.reverse();
+        self
     }
 }
 
@@ -103,4 +116,19 @@ mod tests {
         let v = ls.concave_vertices();
         assert_eq!(v.collect::<Vec<Point>>(), vec![cv1]);
     }
+
+    #[test]
+    fn test_polygon_contains() {
+        let ls = Polygon::new(vec![
+            point(0.0, 0.0),
+            point(10.0, 0.0),
+            point(10.0, 10.0),
+            point(5.0, 5.0),
+            point(0.0, 10.0),
+        ]);
+        assert!(ls.contains(point(2.0, 2.0)));
+        assert!(ls.contains(point(8.0, 8.0)));
+        assert!(!ls.contains(point(5.0, 8.0)));
+        assert!(!ls.contains(point(11.0, 2.0)));
+    }
 }
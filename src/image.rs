@@ -1,19 +1,47 @@
-use std::{fs::File, path::Path, time::Duration};
+use std::{io::Cursor, path::Path, rc::Rc, time::Duration};
+
+use png::DecodingError;
+use serde::Deserialize;
 
 use crate::{
+    error::Error,
     geometry::{point, rect, Point, Rect, Vector},
-    Buffer,
+    sheet, vfs, Buffer,
 };
 
+// what's shown in place of an image that failed to load: a magenta and
+// black checkerboard nobody will mistake for real art
+const PLACEHOLDER_SIZE: usize = 16;
+const PLACEHOLDER_COLOURS: [[u8; 4]; 2] = [[255, 0, 255, 255], [0, 0, 0, 255]];
+
 // simple image buffer: pixels and size
-#[derive(Default, Debug, PartialEq)]
+#[derive(Default, Debug, PartialEq, Clone)]
 pub struct Bitmap {
-    data: Vec<u8>,
+    data: Rc<Vec<u8>>,
+    // where the bitmap starts in `data` and how many pixels a row of `data`
+    // holds, so frames cut out of a sprite sheet can share its pixels
+    origin: (usize, usize),
+    stride: usize,
     size: Rect,
 }
 impl Bitmap {
     pub fn new(buf: Vec<u8>, size: Rect) -> Self {
-        Self { data: buf, size }
+        Self {
+            data: Rc::new(buf),
+            origin: (0, 0),
+            stride: size.w,
+            size,
+        }
+    }
+    // a part of the bitmap, sharing its pixels
+    pub fn region(&self, x: usize, y: usize, size: Rect) -> Self {
+        assert!(x + size.w <= self.cols() && y + size.h <= self.rows());
+        Self {
+            data: Rc::clone(&self.data),
+            origin: (self.origin.0 + x, self.origin.1 + y),
+            stride: self.stride,
+            size,
+        }
     }
     pub fn cols(&self) -> usize {
         let (w, _) = self.size.wh();
@@ -24,79 +52,250 @@ impl Bitmap {
         h
     }
 
+    fn index(&self, x: usize, y: usize) -> usize {
+        ((self.origin.1 + y) * self.stride + self.origin.0 + x) * 4
+    }
     pub fn row_partial(&self, rownum: usize, len: usize) -> &[u8] {
-        let full_row = self.cols() * 4;
-        let a = rownum * full_row;
+        let a = self.index(0, rownum);
         let b = a + (len * 4);
         &self.data[a..b]
     }
+    // all the pixels, only for bitmaps that aren't a region of another one
     pub fn data(&self) -> &[u8] {
+        debug_assert!(self.origin == (0, 0) && self.stride == self.cols());
         &self.data
     }
+    pub fn pixel(&self, x: usize, y: usize) -> &[u8] {
+        let i = self.index(x, y);
+        &self.data[i..i + 4]
+    }
+    // how many bitmaps share these pixels, this one included
+    pub fn handles(&self) -> usize {
+        Rc::strong_count(&self.data)
+    }
 }
 
-#[derive(Default, Debug, PartialEq)]
-struct Frame {
+#[derive(Default, Debug, PartialEq, Clone)]
+pub struct Frame {
     data: Bitmap,
     offset: Point,
     interval: Duration,
 }
 impl Frame {
-    fn new(reader: &mut png::Reader<File>) -> Self {
+    fn new(path: &Path, reader: &mut png::Reader<Cursor<Vec<u8>>>) -> Result<Self, Error> {
         let mut buf = vec![0; reader.output_buffer_size()];
-        let frame = reader.next_frame(&mut buf).unwrap();
+        let frame = reader
+            .next_frame(&mut buf)
+            .map_err(|e| decode_error(path, e))?;
         buf.truncate(frame.buffer_size());
-        let fc = &reader.info().frame_control().unwrap();
-        let interval = Duration::from_secs_f64(fc.delay_num as f64 / fc.delay_den as f64);
+        let fc = reader.info().frame_control().ok_or_else(|| {
+            Error::InvalidAnimation(path.to_owned(), "a frame has no frame control".into())
+        })?;
+        // a denominator of 0 means hundredths of a second
+        let den = match fc.delay_den {
+            0 => 100,
+            d => d,
+        };
+        let interval = Duration::from_secs_f64(fc.delay_num as f64 / den as f64);
         let size = rect(fc.width as usize, fc.height as usize);
-        Frame {
+        Ok(Frame {
             data: Bitmap::new(buf, size),
             offset: point(fc.x_offset as f64, fc.y_offset as f64),
             interval,
+        })
+    }
+    pub fn from_bitmap(data: Bitmap, offset: Point, interval: Duration) -> Self {
+        Self {
+            data,
+            offset,
+            interval,
         }
     }
-    pub fn offset(&self, p: Point) -> Vector {
-        p + self.offset
+    // frames can be smaller than the image, flipping moves them across it
+    pub fn offset(&self, p: Point, scale: f64, mirrored: Option<Rect>) -> Vector {
+        let mut offset = self.offset;
+        if let Some(size) = mirrored {
+            offset.x = size.w as f64 - self.data.cols() as f64 - offset.x;
+        }
+        p + offset * scale
     }
     pub fn data(&self) -> &Bitmap {
         &self.data
     }
 }
 
-#[derive(Default, Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum Playback {
+    #[default]
+    Loop,
+    // plays through once and goes back to the first frame
+    Once,
+    // forwards then backwards, over and over
+    PingPong,
+    // plays through once and stays on the last frame
+    HoldLast,
+}
+
+// how to play an animation, as scripts and cutscenes ask for it
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Control {
+    pub playback: Playback,
+    // the first and last frame to play, all of them without one
+    pub range: Option<(usize, usize)>,
+    // how much faster than its frame durations the animation plays
+    pub speed: f64,
+}
+impl Default for Control {
+    fn default() -> Self {
+        Self {
+            playback: Playback::Loop,
+            range: None,
+            speed: 1.0,
+        }
+    }
+}
+
+// the frames are shared by every copy, the rest is where this copy is in
+// playing them
+#[derive(Debug, PartialEq, Clone)]
 pub struct AnimatedImage {
-    data: Vec<Frame>,
+    data: Rc<[Frame]>,
     size: Rect,
     current_frame_idx: usize,
     last_frame_change: Duration,
+    playback: Playback,
+    // inclusive
+    range: (usize, usize),
+    speed: f64,
+    paused: bool,
+    finished: bool,
+    // going back down while ping-ponging
+    backwards: bool,
 }
 impl AnimatedImage {
-    fn new(frames: Vec<Frame>, size: Rect) -> Self {
+    pub fn new(frames: Vec<Frame>, size: Rect) -> Self {
+        let last = frames.len() - 1;
         Self {
-            data: frames,
+            data: frames.into(),
             size,
             current_frame_idx: 0,
             last_frame_change: Duration::default(),
+            playback: Playback::Loop,
+            range: (0, last),
+            speed: 1.0,
+            paused: false,
+            finished: false,
+            backwards: false,
         }
     }
     pub fn update(&mut self, dt: Duration) {
-        self.last_frame_change += dt;
-        let f = self.current_frame().interval;
-        if self.last_frame_change >= f {
+        if self.paused || self.finished {
+            return;
+        }
+        self.last_frame_change += dt.mul_f64(self.speed);
+        // a fast animation can go past several frames in one update
+        while !self.finished {
+            let f = self.current_frame().interval;
+            if self.last_frame_change < f {
+                break;
+            }
             self.last_frame_change -= f;
-            if self.current_frame_idx == self.data.len() - 1 {
-                self.current_frame_idx = 0;
-            } else {
-                self.current_frame_idx += 1;
+            self.advance();
+            // frames without a duration still get shown for an update
+            if f.is_zero() {
+                break;
             }
         }
     }
+    fn advance(&mut self) {
+        let (first, last) = self.range;
+        let idx = self.current_frame_idx;
+        self.current_frame_idx = match self.playback {
+            Playback::Loop if idx >= last => first,
+            Playback::Once | Playback::HoldLast if idx >= last => {
+                self.finished = true;
+                self.last_frame_change = Duration::ZERO;
+                match self.playback {
+                    Playback::Once => first,
+                    _ => last,
+                }
+            }
+            Playback::PingPong if first == last => first,
+            Playback::PingPong => {
+                if idx >= last {
+                    self.backwards = true;
+                } else if idx <= first {
+                    self.backwards = false;
+                }
+                match self.backwards {
+                    true => idx - 1,
+                    false => idx + 1,
+                }
+            }
+            _ => idx + 1,
+        };
+    }
     fn current_frame(&self) -> &Frame {
         &self.data[self.current_frame_idx]
     }
+    // the current frame and how long it has been showing
+    pub fn position(&self) -> (usize, Duration) {
+        (self.current_frame_idx, self.last_frame_change)
+    }
+    pub fn set_position(&mut self, idx: usize, elapsed: Duration) {
+        self.current_frame_idx = idx.min(self.data.len() - 1);
+        self.last_frame_change = elapsed;
+    }
+    // starts over from the first frame of the range
+    pub fn restart(&mut self) {
+        self.set_position(self.range.0, Duration::ZERO);
+        self.finished = false;
+        self.backwards = false;
+    }
+    // a range past the last frame is cut short
+    pub fn play(&mut self, control: Control) {
+        let last = self.data.len() - 1;
+        let (first, end) = control.range.unwrap_or((0, last));
+        self.range = (first.min(last), end.clamp(first.min(last), last));
+        self.playback = control.playback;
+        self.speed = control.speed.max(0.0);
+        self.paused = false;
+        self.restart();
+    }
+    pub fn playback(&self) -> Playback {
+        self.playback
+    }
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+    // only animations that play through once ever finish
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+    // whether something waiting for the animation has to keep waiting
+    pub fn is_running(&self) -> bool {
+        matches!(self.playback, Playback::Once | Playback::HoldLast) && !self.is_finished()
+    }
+    // how many images share the frames, this one included
+    pub fn handles(&self) -> usize {
+        Rc::strong_count(&self.data)
+    }
+    // jumps to where an animation that plays through once ends
+    pub fn finish(&mut self) {
+        match self.playback {
+            Playback::Once => self.set_position(self.range.0, Duration::ZERO),
+            Playback::HoldLast => self.set_position(self.range.1, Duration::ZERO),
+            Playback::Loop | Playback::PingPong => return,
+        }
+        self.finished = true;
+    }
 }
 
-#[derive(Default, Debug, PartialEq)]
+#[derive(Default, Debug, PartialEq, Clone)]
 pub struct StaticImage {
     data: Bitmap,
 }
@@ -109,74 +308,248 @@ impl StaticImage {
     }
 }
 
-#[derive(Debug, PartialEq)]
+// cloning is cheap: the copy shares the pixels and only gets its own
+// animation state
+#[derive(Debug, PartialEq, Clone)]
 pub enum Image {
     Animated(AnimatedImage),
     Static(StaticImage),
 }
+// nothing to draw, what a released image is replaced with
+impl Default for Image {
+    fn default() -> Self {
+        Self::Static(StaticImage::default())
+    }
+}
 impl Image {
-    pub fn load<T: AsRef<Path>>(path: T) -> Self {
-        let image_file = File::open(path).unwrap();
-        let decoder = png::Decoder::new(image_file);
-        let mut reader = decoder.read_info().unwrap();
+    // a png or apng, or an animation out of a sprite sheet written as
+    // `sheet.ron#walk` or `sheet.json#walk`
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Self, Error> {
+        let path = path.as_ref();
+        if sheet::is_sheet(path) {
+            return Ok(Self::Animated(sheet::load_animation(path)?));
+        }
+        let decoder = png::Decoder::new(Cursor::new(vfs::read(path)?));
+        let mut reader = decoder.read_info().map_err(|e| decode_error(path, e))?;
         let img_info = reader.info();
         let size = rect(img_info.width as usize, img_info.height as usize);
-        if reader.info().is_animated() {
-            let frames: Vec<_> = (0..img_info.animation_control().unwrap().num_frames)
-                .map(|_| Frame::new(&mut reader))
-                .collect();
-            Self::Animated(AnimatedImage::new(frames, size))
+        if img_info.is_animated() {
+            let count = match img_info.animation_control() {
+                Some(ac) if ac.num_frames > 0 => ac.num_frames,
+                _ => {
+                    return Err(Error::InvalidAnimation(
+                        path.to_owned(),
+                        "no frames in the animation control".into(),
+                    ))
+                }
+            };
+            let frames = (0..count)
+                .map(|_| Frame::new(path, &mut reader))
+                .collect::<Result<_, _>>()?;
+            Ok(Self::Animated(AnimatedImage::new(frames, size)))
         } else {
             let mut buf = vec![0; reader.output_buffer_size()];
-            reader.next_frame(&mut buf).unwrap();
+            reader
+                .next_frame(&mut buf)
+                .map_err(|e| decode_error(path, e))?;
             let bmp = Bitmap::new(buf, size);
-            Self::Static(StaticImage::new(bmp))
+            Ok(Self::Static(StaticImage::new(bmp)))
         }
     }
+    pub fn placeholder() -> Self {
+        let n = PLACEHOLDER_SIZE;
+        let buf = (0..n * n)
+            .flat_map(|i| PLACEHOLDER_COLOURS[(i % n / 4 + i / n / 4) % 2])
+            .collect();
+        Self::Static(StaticImage::new(Bitmap::new(buf, rect(n, n))))
+    }
     pub fn draw(&self, buf: &mut Buffer, p: Point) {
+        self.draw_scaled(buf, p, 1.0, false);
+    }
+    pub fn draw_scaled(&self, buf: &mut Buffer, p: Point, scale: f64, mirrored: bool) {
         match self {
-            Self::Static(i) => buf.draw_bmp(i.data(), p),
+            Self::Static(i) => buf.draw_bmp_scaled(i.data(), p, scale, mirrored),
             Self::Animated(a) => {
                 let f = a.current_frame();
-                let p = f.offset(p);
-                buf.draw_bmp(f.data(), p);
+                let p = f.offset(p, scale, mirrored.then_some(a.size));
+                buf.draw_bmp_scaled(f.data(), p, scale, mirrored);
             }
         }
     }
+    pub fn animation(&self) -> Option<&AnimatedImage> {
+        match self {
+            Self::Animated(a) => Some(a),
+            Self::Static(_) => None,
+        }
+    }
+    pub fn animation_mut(&mut self) -> Option<&mut AnimatedImage> {
+        match self {
+            Self::Animated(a) => Some(a),
+            Self::Static(_) => None,
+        }
+    }
     pub fn size(&self) -> Rect {
         match self {
             Self::Static(i) => i.data.size,
             Self::Animated(i) => i.size,
         }
     }
+    pub fn handles(&self) -> usize {
+        match self {
+            Self::Static(i) => i.data.handles(),
+            Self::Animated(i) => i.handles(),
+        }
+    }
+}
+
+// the decoder's own io errors are reported like any other
+fn decode_error(path: &Path, e: DecodingError) -> Error {
+    match e {
+        DecodingError::IoError(e) => Error::Io(path.to_owned(), e),
+        e => Error::Decode(path.to_owned(), e.to_string()),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
 
-    use crate::image::Image;
+    use crate::{
+        error::Error,
+        geometry::{point, rect, vector},
+    };
+
+    use super::{AnimatedImage, Bitmap, Control, Frame, Image, Playback};
 
     #[test]
     fn test_load_static_image() {
-        let image = Image::load("resources/fox.png");
+        let image = Image::load("resources/fox.png").unwrap();
         assert!(matches!(image, Image::Static(_)));
     }
 
     #[test]
     fn test_load_animated_image() {
-        let image = Image::load("resources/ball.png");
+        let image = Image::load("resources/ball.png").unwrap();
         assert!(matches!(image, Image::Animated(_)));
     }
 
+    #[test]
+    fn test_load_errors() {
+        let image = Image::load("resources/missing.png");
+        assert!(matches!(image, Err(Error::Io(_, _))));
+        let image = Image::load("resources/font.bdf");
+        assert!(matches!(image, Err(Error::Decode(_, _))));
+        let image = Image::load("resources/fox_sheet.json#run");
+        assert!(matches!(image, Err(Error::InvalidScene(_, _))));
+
+        assert_eq!(Image::placeholder().size(), rect(16, 16));
+    }
+
     #[test]
     fn test_animated_image_frame() {
-        if let Image::Animated(mut image) = Image::load("resources/ball.png") {
-            let dt = Duration::from_secs_f64(1.0);
-            image.update(dt);
+        let Image::Animated(mut image) = Image::load("resources/ball.png").unwrap() else {
+            panic!("not animated");
+        };
+        // 75ms frames, a second covers 13 of them
+        let dt = Duration::from_secs_f64(1.0);
+        image.update(dt);
 
-            assert_eq!(image.current_frame_idx, 1);
-            assert_eq!(image.last_frame_change, Duration::from_millis(925));
-        }
+        assert_eq!(image.current_frame_idx, 13);
+        assert_eq!(image.last_frame_change, Duration::from_millis(25));
+    }
+
+    // steps through the frames shown on every tick of one frame's length
+    fn frames(image: &mut AnimatedImage, ticks: usize) -> Vec<usize> {
+        let dt = image.current_frame().interval;
+        (0..ticks)
+            .map(|_| {
+                image.update(dt);
+                image.current_frame_idx
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_playback() {
+        let Image::Animated(mut image) = Image::load("resources/fox_walk.png").unwrap() else {
+            panic!("not animated");
+        };
+        assert_eq!(frames(&mut image, 5), vec![1, 2, 3, 0, 1]);
+
+        image.play(Control {
+            playback: Playback::Once,
+            ..Control::default()
+        });
+        assert!(image.is_running());
+        assert_eq!(frames(&mut image, 5), vec![1, 2, 3, 0, 0]);
+        assert!(image.is_finished() && !image.is_running());
+
+        image.play(Control {
+            playback: Playback::HoldLast,
+            range: Some((1, 2)),
+            ..Control::default()
+        });
+        assert_eq!(image.current_frame_idx, 1);
+        assert_eq!(frames(&mut image, 3), vec![2, 2, 2]);
+        assert!(image.is_finished());
+
+        image.play(Control {
+            playback: Playback::PingPong,
+            ..Control::default()
+        });
+        assert_eq!(frames(&mut image, 7), vec![1, 2, 3, 2, 1, 0, 1]);
+
+        image.pause();
+        assert_eq!(frames(&mut image, 2), vec![1, 1]);
+        image.resume();
+        image.play(Control {
+            speed: 0.5,
+            range: Some((2, 9)),
+            ..Control::default()
+        });
+        assert_eq!(frames(&mut image, 4), vec![2, 3, 3, 2]);
+
+        // faster than a tick per frame skips frames instead of lagging
+        image.play(Control {
+            speed: 3.0,
+            ..Control::default()
+        });
+        assert_eq!(frames(&mut image, 3), vec![3, 2, 1]);
+        image.play(Control {
+            playback: Playback::Once,
+            speed: 10.0,
+            ..Control::default()
+        });
+        assert_eq!(frames(&mut image, 2), vec![0, 0]);
+        assert!(image.is_finished());
+    }
+
+    #[test]
+    fn test_mirrored_offset() {
+        // a frame wider than the canvas sticks out on the other side
+        let frame = Frame::from_bitmap(
+            Bitmap::new(vec![0; 12 * 2 * 4], rect(12, 2)),
+            point(1.0, 0.0),
+            Duration::ZERO,
+        );
+        let p = point(0.0, 0.0);
+        assert_eq!(frame.offset(p, 1.0, None), vector(1.0, 0.0));
+        assert_eq!(frame.offset(p, 2.0, Some(rect(10, 2))), vector(-6.0, 0.0));
+    }
+
+    #[test]
+    fn test_finish() {
+        let Image::Animated(mut image) = Image::load("resources/fox_walk.png").unwrap() else {
+            panic!("not animated");
+        };
+        image.finish();
+        assert!(!image.is_finished());
+        image.play(Control {
+            playback: Playback::HoldLast,
+            ..Control::default()
+        });
+        image.finish();
+        assert_eq!(image.position(), (3, Duration::ZERO));
+        assert!(image.is_finished());
     }
 }
@@ -1,45 +1,133 @@
+use archive::Archive;
+use backend::{Backend, MemoryBackend, WindowBackend};
 use buffer::Buffer;
+use config::Config;
+use error::Error;
 use game_state::GameState;
+use watch::Watcher;
 
 use winit::{
+    dpi::LogicalSize,
     event::{ElementState, Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::{Fullscreen, WindowBuilder},
 };
 
+mod archive;
+mod assets;
+mod backend;
 mod buffer;
+mod config;
+mod cutscene;
 mod error;
 mod game;
 mod game_state;
 mod geometry;
 mod image;
+mod save;
+mod scene;
+mod script;
+mod sheet;
+#[cfg(test)]
+mod snapshot;
 mod text;
+mod vfs;
+mod watch;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // build an archive out of a directory instead of playing, without
+    // needing a config to do it
+    if let [flag, dir, out] = &args[..] {
+        if flag == "--pack" {
+            if let Err(e) = pack(dir, out) {
+                eprintln!("{}", e);
+            }
+            return;
+        }
+    }
+    let (config, args) = match Config::from_args(&args) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    // loose files are what's being edited while hot reloading
+    if !config.hot_reload && config.archive.exists() {
+        match Archive::open(&config.archive) {
+            Ok(a) => vfs::mount(a),
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        }
+    }
+    let mut game_state = match GameState::new("resources/game.ron") {
+        Ok(g) => g,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let size = config.screen();
+    game_state.set_screen(size);
+
+    // render the first frame without opening a window
+    if let [flag, path] = &args[..] {
+        if flag == "--screenshot" {
+            let mut buffer = Buffer::new(size);
+            let mut backend = MemoryBackend::new();
+            game_state.draw(&mut buffer);
+            if let Err(e) = backend
+                .present(&buffer)
+                .and_then(|_| backend.save_png(path))
+            {
+                eprintln!("{}", e);
+            }
+            return;
+        }
+    }
+
     let event_loop = EventLoop::new();
-    let monitor = event_loop
-        .available_monitors()
-        .next()
-        .expect("no monitor found!");
     let window = {
-        WindowBuilder::new()
-            .with_fullscreen(Some(Fullscreen::Borderless(Some(monitor))))
+        let inner = LogicalSize::new(size.w as u32, size.h as u32);
+        let builder = WindowBuilder::new()
+            .with_title("crust engine")
+            .with_inner_size(inner)
+            .with_min_inner_size(inner);
+        let fullscreen = config.fullscreen.then_some(Fullscreen::Borderless(None));
+        builder
+            .with_fullscreen(fullscreen)
             .build(&event_loop)
             .unwrap()
     };
-    let mut buffer = Buffer::new(&window);
-    let mut game_state = GameState::new();
+    let mut watcher = config.hot_reload.then(|| Watcher::new("resources"));
+    let mut buffer = Buffer::new(size);
+    let mut backend = match WindowBackend::new(&window, &config) {
+        Ok(b) => b,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
 
     event_loop.run(move |event, _, control_flow| {
         control_flow.set_wait();
         match event {
             Event::WindowEvent { event, .. } => match event {
                 WindowEvent::Resized(size) => {
-                    if let Err(_) = buffer.resize(size) {
+                    if let Err(_) = backend.resize(size) {
                         *control_flow = ControlFlow::Exit;
                         return;
                     }
                 }
+                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                    if let Err(e) = backend.resize(*new_inner_size) {
+                        eprintln!("{}", e);
+                        *control_flow = ControlFlow::Exit;
+                    }
+                }
                 WindowEvent::KeyboardInput {
                     input:
                         KeyboardInput {
@@ -49,8 +137,17 @@ fn main() {
                         },
                     ..
                 } => game_state.exit_requested = true,
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            virtual_keycode: Some(key),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => game_state.key_pressed(key),
                 WindowEvent::CursorMoved { position, .. } => {
-                    if let Some(pos) = buffer.convert_pos(position) {
+                    if let Some(pos) = backend.convert_pos(position) {
                         game_state.mouse_over(pos)
                     }
                 }
@@ -63,13 +160,22 @@ fn main() {
             },
             Event::RedrawRequested(_) => {
                 game_state.draw(&mut buffer);
-                buffer.render()
+                if let Err(e) = backend.present(&buffer) {
+                    eprintln!("{}", e);
+                    *control_flow = ControlFlow::Exit;
+                }
             }
             Event::MainEventsCleared => {
                 if game_state.exit_requested {
                     *control_flow = ControlFlow::Exit;
                     return;
                 }
+                if let Some(watcher) = &mut watcher {
+                    let changed = watcher.poll();
+                    if !changed.is_empty() {
+                        game_state.reload(&changed);
+                    }
+                }
                 game_state.tick();
                 window.request_redraw();
             }
@@ -77,3 +183,8 @@ fn main() {
         }
     })
 }
+
+fn pack(dir: &str, out: &str) -> Result<(), Error> {
+    let data = archive::pack(dir.as_ref())?;
+    std::fs::write(out, data).map_err(|e| Error::Io(out.into(), e))
+}
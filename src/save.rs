@@ -0,0 +1,194 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::Error, image::Image, scene::Coord};
+
+// bump this when the format changes in a way `#[serde(default)]` can't paper
+// over, and teach `migrate` how to bring the old version up to date
+pub const SAVE_VERSION: u32 = 2;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    pub room: String,
+    pub character: ActorState,
+    pub rooms: HashMap<String, RoomState>,
+    pub inventory: Vec<String>,
+    #[serde(default)]
+    pub flags: BTreeSet<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomState {
+    pub objects: Vec<ObjectState>,
+    pub actors: Vec<ActorState>,
+    // whether each named walk area is switched on
+    #[serde(default)]
+    pub areas: BTreeMap<String, bool>,
+}
+
+// objects are matched up by name, missing ones have been picked up
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectState {
+    pub name: String,
+    pub location: Coord,
+    #[serde(default)]
+    pub frame: Option<FrameState>,
+}
+
+// room actors are matched up by name, ones without are scenery and start
+// where the room puts them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActorState {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub location: Coord,
+    #[serde(default)]
+    pub path: Vec<Coord>,
+    #[serde(default)]
+    pub frame: Option<FrameState>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FrameState {
+    pub index: usize,
+    pub elapsed_ms: u64,
+}
+impl FrameState {
+    pub fn of(image: &Image) -> Option<Self> {
+        image.animation().map(|a| {
+            let (index, elapsed) = a.position();
+            Self {
+                index,
+                elapsed_ms: elapsed.as_millis() as u64,
+            }
+        })
+    }
+    pub fn apply(state: Option<Self>, image: &mut Image) {
+        if let (Some(s), Some(a)) = (state, image.animation_mut()) {
+            a.set_position(s.index, Duration::from_millis(s.elapsed_ms));
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+pub fn slot_path<T: AsRef<Path>>(dir: T, slot: usize) -> PathBuf {
+    dir.as_ref().join(format!("slot{}.ron", slot))
+}
+
+pub fn write<T: AsRef<Path>>(path: T, save: &SaveGame) -> Result<(), Error> {
+    let path = path.as_ref();
+    let s = ron::ser::to_string_pretty(save, ron::ser::PrettyConfig::default())
+        .map_err(|e| Error::InvalidSave(path.to_owned(), e.to_string()))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| Error::Io(dir.to_owned(), e))?;
+    }
+    fs::write(path, s).map_err(|e| Error::Io(path.to_owned(), e))
+}
+
+pub fn read<T: AsRef<Path>>(path: T) -> Result<SaveGame, Error> {
+    let path = path.as_ref();
+    let s = fs::read_to_string(path).map_err(|e| Error::Io(path.to_owned(), e))?;
+    parse(path, &s)
+}
+
+fn parse(path: &Path, s: &str) -> Result<SaveGame, Error> {
+    let invalid = |e: ron::error::SpannedError| Error::InvalidSave(path.to_owned(), e.to_string());
+    let header: Header = ron::from_str(s).map_err(invalid)?;
+    if header.version > SAVE_VERSION {
+        return Err(Error::InvalidSave(
+            path.to_owned(),
+            format!("made by a newer version of the game ({})", header.version),
+        ));
+    }
+    let mut save: SaveGame = ron::from_str(s).map_err(invalid)?;
+    migrate(&mut save);
+    Ok(save)
+}
+
+fn migrate(save: &mut SaveGame) {
+    // version 1 matched actors up by their order in the room, which a changed
+    // room file gets wrong; without names they start over
+    save.version = SAVE_VERSION;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, BTreeSet, HashMap},
+        path::Path,
+    };
+
+    use crate::error::Error;
+
+    use super::{parse, ActorState, FrameState, RoomState, SaveGame, SAVE_VERSION};
+
+    fn save_game() -> SaveGame {
+        SaveGame {
+            version: SAVE_VERSION,
+            room: "start".to_owned(),
+            character: ActorState {
+                name: None,
+                location: (150.0, 150.0),
+                path: vec![(200.0, 200.0)],
+                frame: None,
+            },
+            rooms: HashMap::from([(
+                "start".to_owned(),
+                RoomState {
+                    objects: vec![],
+                    actors: vec![ActorState {
+                        name: Some("fennec".to_owned()),
+                        location: (10.0, 10.0),
+                        path: vec![],
+                        frame: Some(FrameState {
+                            index: 2,
+                            elapsed_ms: 30,
+                        }),
+                    }],
+                    areas: BTreeMap::from([("bridge".to_owned(), true)]),
+                },
+            )]),
+            inventory: vec!["ball".to_owned()],
+            flags: BTreeSet::from(["met_fennec".to_owned()]),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let save = save_game();
+        let s = ron::ser::to_string(&save).unwrap();
+        assert_eq!(parse(Path::new("slot1.ron"), &s).unwrap(), save);
+    }
+
+    #[test]
+    fn test_versions() {
+        let mut save = save_game();
+        save.version = SAVE_VERSION + 1;
+        let s = ron::ser::to_string(&save).unwrap();
+        let res = parse(Path::new("slot1.ron"), &s);
+        assert!(matches!(res, Err(Error::InvalidSave(_, _))));
+
+        // fields added after a save was written fall back to their defaults
+        let old = r#"(
+            version: 1,
+            room: "start",
+            character: (location: (1.0, 2.0)),
+            rooms: {},
+            inventory: [],
+        )"#;
+        let save = parse(Path::new("slot1.ron"), old).unwrap();
+        assert!(save.flags.is_empty());
+        assert!(save.character.path.is_empty());
+    }
+}
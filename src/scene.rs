@@ -0,0 +1,220 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    error::Error,
+    game::{AnimationData, Depth, Response, Verb},
+    geometry::{Point, Polygon},
+    sheet, vfs,
+};
+
+// a point as written in scene files: `(x, y)`
+pub type Coord = (f64, f64);
+
+#[derive(Debug, Deserialize)]
+pub struct GameData {
+    pub character: CharacterData,
+    pub start: String,
+    pub rooms: HashMap<String, PathBuf>,
+    #[serde(default)]
+    pub items: HashMap<String, ItemData>,
+    #[serde(default)]
+    pub combinations: Vec<CombinationData>,
+    #[serde(default)]
+    pub dialogues: HashMap<String, PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ItemData {
+    pub name: String,
+    pub icon: PathBuf,
+    pub description: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CombinationData {
+    pub items: (String, String),
+    pub result: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CharacterData {
+    pub image: PathBuf,
+    pub speed: f64,
+    #[serde(default)]
+    pub animations: AnimationData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoomData {
+    pub background: PathBuf,
+    pub spawn: Coord,
+    #[serde(default)]
+    pub entries: HashMap<String, Coord>,
+    pub walkbox: WalkBoxData,
+    #[serde(default)]
+    pub exits: Vec<ExitData>,
+    #[serde(default)]
+    pub actors: Vec<ActorData>,
+    #[serde(default)]
+    pub objects: Vec<ObjectData>,
+    // runs every time the player enters the room
+    #[serde(default)]
+    pub script: Option<PathBuf>,
+    #[serde(default)]
+    pub masks: Vec<MaskData>,
+    #[serde(default)]
+    pub layers: Vec<LayerData>,
+}
+
+// an extra image scrolling along with the background, see `Layer`
+#[derive(Debug, Deserialize)]
+pub struct LayerData {
+    pub image: PathBuf,
+    #[serde(default)]
+    pub position: Coord,
+    // 1 by default, moving with the background
+    #[serde(default)]
+    pub scroll: Option<f64>,
+    #[serde(default)]
+    pub depth: Depth,
+}
+
+// a part of the background that actors can walk behind, anyone whose feet
+// are above the baseline (the bottom of the area by default) is covered by it
+#[derive(Debug, Deserialize)]
+pub struct MaskData {
+    pub area: Vec<Coord>,
+    #[serde(default)]
+    pub baseline: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WalkBoxData {
+    pub exterior: Vec<Coord>,
+    #[serde(default)]
+    pub interior: Vec<Vec<Coord>>,
+    #[serde(default)]
+    pub scale: Option<ScaleData>,
+    // more floor joined on wherever it touches or overlaps, scripts switch
+    // these with `enable`/`disable`
+    #[serde(default)]
+    pub areas: Vec<AreaData>,
+    #[serde(default)]
+    pub pathfinding: Pathfinding,
+}
+
+// how paths through a room are found
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+pub enum Pathfinding {
+    // straight lines between the corners that can see each other, plenty for
+    // a room with a few corners
+    #[default]
+    Graph,
+    // the floor cut into triangles, quicker to build and search when there
+    // are lots of corners but paths can come out a little longer; areas have
+    // to meet along their edges
+    NavMesh,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AreaData {
+    pub name: String,
+    pub exterior: Vec<Coord>,
+    #[serde(default)]
+    pub interior: Vec<Vec<Coord>>,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+// (y, scale) at the back and the front of the room
+#[derive(Debug, Deserialize)]
+pub struct ScaleData {
+    pub far: (f64, f64),
+    pub near: (f64, f64),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExitData {
+    pub area: Vec<Coord>,
+    pub room: String,
+    #[serde(default)]
+    pub entry: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActorData {
+    pub image: PathBuf,
+    pub location: Coord,
+    // draw order, the y of the actor's feet unless given
+    #[serde(default)]
+    pub z: Option<f64>,
+    #[serde(default)]
+    pub animations: AnimationData,
+    #[serde(default)]
+    pub speed: Option<f64>,
+    // actors without a name are scenery and can't be interacted with
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub walk_to: Option<Coord>,
+    #[serde(default)]
+    pub responses: HashMap<Verb, Response>,
+    #[serde(default)]
+    pub uses: HashMap<String, Response>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ObjectData {
+    pub name: String,
+    pub image: PathBuf,
+    pub location: Coord,
+    // draw order, the y of the object's bottom edge unless given
+    #[serde(default)]
+    pub z: Option<f64>,
+    #[serde(default)]
+    pub walk_to: Option<Coord>,
+    #[serde(default)]
+    pub responses: HashMap<Verb, Response>,
+    #[serde(default)]
+    pub uses: HashMap<String, Response>,
+}
+
+pub fn load<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<T, Error> {
+    let path = path.as_ref();
+    let s = vfs::read_to_string(path)?;
+    parse(path, &s)
+}
+
+pub fn parse<T: DeserializeOwned>(path: &Path, s: &str) -> Result<T, Error> {
+    ron::from_str(s).map_err(|e| Error::InvalidScene(path.to_owned(), e.to_string()))
+}
+
+// fails early with the offending path instead of panicking inside the image
+// decoder; only checks the file is there, decoding is left to `Assets`
+pub fn asset<P: AsRef<Path>>(path: P) -> Result<PathBuf, Error> {
+    let path = path.as_ref();
+    let (file, _) = sheet::split(path);
+    vfs::check(file)?;
+    Ok(path.to_owned())
+}
+
+pub fn polygon(path: &Path, vertices: &[Coord]) -> Result<Polygon, Error> {
+    if vertices.len() < 3 {
+        return Err(Error::InvalidScene(
+            path.to_owned(),
+            format!("polygon needs at least 3 vertices, got {}", vertices.len()),
+        ));
+    }
+    Ok(Polygon::new(
+        vertices.iter().map(|&c| Point::from(c)).collect(),
+    ))
+}
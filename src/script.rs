@@ -0,0 +1,529 @@
+// a tiny line based language for room and object logic, one command per line:
+//
+//   walk <actor> <x> <y>     walks there and waits until arrived
+//   say <actor> "<text>"     waits until the line has been said
+//   anim <actor> <png> [loop|once|pingpong|hold] [frames <first> <last>] [speed <x>]
+//                            swaps in another animation, waits for it to
+//                            finish if it only plays once
+//   pause <actor> / resume <actor>
+//   set <flag> / unset <flag>
+//   enable <area> / disable <area>
+//                            switches a walk area of the current room
+//   if [not] <flag> ... [else ...] end
+//   room <room> [entry]
+//   wait <ms>
+//   cutscene <file>          plays a cutscene and waits for it to finish
+//
+// `player` is the player's character, any other actor is looked up by name
+// in the current room. `#` starts a comment.
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    cutscene::Timeline,
+    error::Error,
+    geometry::{point, Point},
+    image::{Control, Playback},
+    scene, vfs,
+};
+
+// what scripts and cutscenes can do to the world
+pub trait Host {
+    // false if there is no such actor, the command is skipped then
+    fn walk(&mut self, actor: &str, to: Point) -> bool;
+    fn is_walking(&self, actor: &str) -> bool;
+    // puts the actor where walking there would end up, straight away
+    fn place(&mut self, actor: &str, to: Point);
+    fn say(&mut self, actor: &str, text: &str) -> bool;
+    fn is_talking(&self) -> bool;
+    fn play(&mut self, actor: &str, image: &Path, control: Control);
+    // whether the actor's animation is one that finishes and hasn't yet
+    fn is_animating(&self, actor: &str) -> bool;
+    fn finish_animation(&mut self, actor: &str);
+    fn pause_animation(&mut self, actor: &str, paused: bool);
+    fn flag(&self, name: &str) -> bool;
+    fn set_flag(&mut self, name: &str, value: bool);
+    fn set_walk_area(&mut self, area: &str, enabled: bool);
+    fn change_room(&mut self, room: &str, entry: Option<&str>);
+    fn fade(&self) -> f64;
+    fn set_fade(&mut self, level: f64);
+    fn start_cutscene(&mut self, timeline: &Timeline);
+    fn in_cutscene(&self) -> bool;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    Walk(String, Point),
+    Say(String, String),
+    Play(String, PathBuf, Control),
+    Pause(String, bool),
+    SetFlag(String, bool),
+    WalkArea(String, bool),
+    Room(String, Option<String>),
+    Wait(Duration),
+    Cutscene(Timeline),
+    // jumps to the op at the index unless the flag has the given value
+    Branch(String, bool, usize),
+    Jump(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    ops: Vec<Op>,
+}
+impl Program {
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let s = vfs::read_to_string(path)?;
+        Self::parse(path, &s)
+    }
+    pub fn parse(path: &Path, s: &str) -> Result<Self, Error> {
+        let mut ops = vec![];
+        // indices of the branches and jumps still waiting for their `else`/`end`
+        let mut open: Vec<usize> = vec![];
+        for (n, line) in s.lines().enumerate() {
+            let invalid = |reason: String| {
+                Error::InvalidScene(path.to_owned(), format!("line {}: {}", n + 1, reason))
+            };
+            let words = tokenize(line).map_err(invalid)?;
+            let (command, args) = match words.split_first() {
+                Some((c, args)) => (c.as_str(), args),
+                None => continue,
+            };
+            let arity = |min: usize, max: usize| {
+                if args.len() < min || args.len() > max {
+                    Err(invalid(format!(
+                        "wrong number of arguments to '{}'",
+                        command
+                    )))
+                } else {
+                    Ok(())
+                }
+            };
+            let number = |s: &str| {
+                s.parse::<f64>()
+                    .map_err(|_| invalid(format!("'{}' is not a number", s)))
+            };
+            let op = match command {
+                "walk" => {
+                    arity(3, 3)?;
+                    Op::Walk(args[0].clone(), point(number(&args[1])?, number(&args[2])?))
+                }
+                "say" => {
+                    arity(2, 2)?;
+                    Op::Say(args[0].clone(), args[1].clone())
+                }
+                "anim" => {
+                    arity(2, 8)?;
+                    let mut control = Control::default();
+                    let mut options = args[2..].iter();
+                    while let Some(option) = options.next() {
+                        let mut value = || {
+                            options
+                                .next()
+                                .ok_or_else(|| invalid(format!("'{}' needs a value", option)))
+                        };
+                        match option.as_str() {
+                            "loop" => control.playback = Playback::Loop,
+                            "once" => control.playback = Playback::Once,
+                            "pingpong" => control.playback = Playback::PingPong,
+                            "hold" => control.playback = Playback::HoldLast,
+                            "frames" => {
+                                let first = number(value()?)?;
+                                let last = number(value()?)?;
+                                if first < 0.0 || last < first {
+                                    return Err(invalid(format!(
+                                        "'{} {}' is not a frame range",
+                                        first, last
+                                    )));
+                                }
+                                control.range = Some((first as usize, last as usize));
+                            }
+                            "speed" => control.speed = number(value()?)?.max(0.0),
+                            o => return Err(invalid(format!("unknown option '{}'", o))),
+                        }
+                    }
+                    Op::Play(args[0].clone(), scene::asset(&args[1])?, control)
+                }
+                "pause" | "resume" => {
+                    arity(1, 1)?;
+                    Op::Pause(args[0].clone(), command == "pause")
+                }
+                "set" | "unset" => {
+                    arity(1, 1)?;
+                    Op::SetFlag(args[0].clone(), command == "set")
+                }
+                "enable" | "disable" => {
+                    arity(1, 1)?;
+                    Op::WalkArea(args[0].clone(), command == "enable")
+                }
+                "room" => {
+                    arity(1, 2)?;
+                    Op::Room(args[0].clone(), args.get(1).cloned())
+                }
+                "wait" => {
+                    arity(1, 1)?;
+                    let ms = number(&args[0])?;
+                    Op::Wait(Duration::from_millis(ms.max(0.0) as u64))
+                }
+                "cutscene" => {
+                    arity(1, 1)?;
+                    Op::Cutscene(Timeline::load(&args[0])?)
+                }
+                "if" => {
+                    let (flag, value) = match args {
+                        [not, flag] if not == "not" => (flag.clone(), false),
+                        [flag] => (flag.clone(), true),
+                        _ => return Err(invalid("expected 'if [not] <flag>'".to_owned())),
+                    };
+                    open.push(ops.len());
+                    Op::Branch(flag, value, 0)
+                }
+                "else" => {
+                    arity(0, 0)?;
+                    let branch = open
+                        .pop()
+                        .ok_or_else(|| invalid("'else' without 'if'".into()))?;
+                    if !matches!(ops[branch], Op::Branch(..)) {
+                        return Err(invalid("second 'else' for the same 'if'".to_owned()));
+                    }
+                    open.push(ops.len());
+                    ops.push(Op::Jump(0));
+                    let target = ops.len();
+                    set_target(&mut ops[branch], target);
+                    continue;
+                }
+                "end" => {
+                    arity(0, 0)?;
+                    let open = open
+                        .pop()
+                        .ok_or_else(|| invalid("'end' without 'if'".into()))?;
+                    let target = ops.len();
+                    set_target(&mut ops[open], target);
+                    continue;
+                }
+                _ => return Err(invalid(format!("unknown command '{}'", command))),
+            };
+            ops.push(op);
+        }
+        if !open.is_empty() {
+            return Err(Error::InvalidScene(
+                path.to_owned(),
+                "'if' without 'end'".to_owned(),
+            ));
+        }
+        Ok(Self { ops })
+    }
+}
+
+fn set_target(op: &mut Op, target: usize) {
+    match op {
+        Op::Branch(_, _, t) | Op::Jump(t) => *t = target,
+        _ => unreachable!(),
+    }
+}
+
+// splits on whitespace, keeping "quoted text" together
+fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut rest = line.trim_start();
+    while !rest.is_empty() && !rest.starts_with('#') {
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted
+                .find('"')
+                .ok_or_else(|| "unterminated string".to_owned())?;
+            words.push(quoted[..end].to_owned());
+            rest = &quoted[end + 1..];
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            words.push(rest[..end].to_owned());
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(words)
+}
+
+// a running program, resumed once per tick until it runs out of commands
+#[derive(Debug)]
+pub struct Script {
+    program: Program,
+    pc: usize,
+    // whether the current command has been kicked off and is being waited on
+    started: bool,
+    waited: Duration,
+}
+impl Script {
+    pub fn new(program: Program) -> Self {
+        Self {
+            program,
+            pc: 0,
+            started: false,
+            waited: Duration::ZERO,
+        }
+    }
+    // runs until a command has to wait for later ticks, returns false once
+    // the script is done
+    pub fn resume<H: Host>(&mut self, host: &mut H, dt: Duration) -> bool {
+        self.waited += dt;
+        while let Some(op) = self.program.ops.get(self.pc) {
+            let first = !self.started;
+            if first {
+                self.started = true;
+                self.waited = Duration::ZERO;
+            }
+            let mut next = self.pc + 1;
+            match op {
+                // a command the host couldn't start has nothing to wait for
+                Op::Walk(actor, to) => {
+                    let started = !first || host.walk(actor, *to);
+                    if started && host.is_walking(actor) {
+                        return true;
+                    }
+                }
+                Op::Say(actor, text) => {
+                    let started = !first || host.say(actor, text);
+                    if started && host.is_talking() {
+                        return true;
+                    }
+                }
+                Op::Play(actor, image, control) => {
+                    if first {
+                        host.play(actor, image, *control);
+                    }
+                    if host.is_animating(actor) {
+                        return true;
+                    }
+                }
+                Op::Pause(actor, paused) => host.pause_animation(actor, *paused),
+                Op::SetFlag(flag, value) => host.set_flag(flag, *value),
+                Op::WalkArea(area, enabled) => host.set_walk_area(area, *enabled),
+                Op::Room(room, entry) => host.change_room(room, entry.as_deref()),
+                Op::Wait(d) => {
+                    if self.waited < *d {
+                        return true;
+                    }
+                }
+                Op::Cutscene(timeline) => {
+                    if first {
+                        host.start_cutscene(timeline);
+                    }
+                    if host.in_cutscene() {
+                        return true;
+                    }
+                }
+                Op::Branch(flag, value, target) => {
+                    if host.flag(flag) != *value {
+                        next = *target;
+                    }
+                }
+                Op::Jump(target) => next = *target,
+            }
+            self.pc = next;
+            self.started = false;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeSet, HashMap},
+        path::Path,
+        time::Duration,
+    };
+
+    use crate::{
+        cutscene::Timeline,
+        error::Error,
+        geometry::{point, Point},
+        image::{Control, Playback},
+    };
+
+    use super::{Host, Op, Program, Script};
+
+    #[derive(Default)]
+    struct TestHost {
+        walking: HashMap<String, Point>,
+        talking: bool,
+        said: Vec<String>,
+        flags: BTreeSet<String>,
+        room: String,
+        fade: f64,
+        animating: BTreeSet<String>,
+    }
+    impl Host for TestHost {
+        fn walk(&mut self, actor: &str, to: Point) -> bool {
+            self.walking.insert(actor.to_owned(), to);
+            true
+        }
+        fn is_walking(&self, actor: &str) -> bool {
+            self.walking.contains_key(actor)
+        }
+        fn place(&mut self, actor: &str, _: Point) {
+            self.walking.remove(actor);
+        }
+        fn say(&mut self, _: &str, text: &str) -> bool {
+            self.said.push(text.to_owned());
+            self.talking = true;
+            true
+        }
+        fn is_talking(&self) -> bool {
+            self.talking
+        }
+        fn play(&mut self, actor: &str, _: &Path, control: Control) {
+            if control.playback != Playback::Loop {
+                self.animating.insert(actor.to_owned());
+            }
+        }
+        fn is_animating(&self, actor: &str) -> bool {
+            self.animating.contains(actor)
+        }
+        fn finish_animation(&mut self, actor: &str) {
+            self.animating.remove(actor);
+        }
+        fn pause_animation(&mut self, _: &str, _: bool) {}
+        fn flag(&self, name: &str) -> bool {
+            self.flags.contains(name)
+        }
+        fn set_flag(&mut self, name: &str, value: bool) {
+            if value {
+                self.flags.insert(name.to_owned());
+            } else {
+                self.flags.remove(name);
+            }
+        }
+        fn set_walk_area(&mut self, _: &str, _: bool) {}
+        fn change_room(&mut self, room: &str, _: Option<&str>) {
+            self.room = room.to_owned();
+        }
+        fn fade(&self) -> f64 {
+            self.fade
+        }
+        fn set_fade(&mut self, level: f64) {
+            self.fade = level;
+        }
+        fn start_cutscene(&mut self, _: &Timeline) {}
+        fn in_cutscene(&self) -> bool {
+            false
+        }
+    }
+
+    fn parse(s: &str) -> Result<Program, Error> {
+        Program::parse(Path::new("test.script"), s)
+    }
+
+    #[test]
+    fn test_parse() {
+        let program = parse(
+            r#"
+            # comment
+            walk player 10 20.5
+            say fennec "Hello, you."
+            if not met
+                set met
+            else
+                wait 100
+            end
+            disable bridge
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            program.ops,
+            vec![
+                Op::Walk("player".to_owned(), point(10.0, 20.5)),
+                Op::Say("fennec".to_owned(), "Hello, you.".to_owned()),
+                Op::Branch("met".to_owned(), false, 5),
+                Op::SetFlag("met".to_owned(), true),
+                Op::Jump(6),
+                Op::Wait(Duration::from_millis(100)),
+                Op::WalkArea("bridge".to_owned(), false),
+            ]
+        );
+
+        for s in [
+            "jump",
+            "enable",
+            "walk player 10",
+            "walk player ten 10",
+            "say player \"unterminated",
+            "if met",
+            "end",
+            "if met\nelse\nelse\nend",
+            "anim player resources/missing.png",
+            "anim player resources/fox.png twice",
+            "anim player resources/fox.png frames 3 1",
+            "anim player resources/fox.png speed",
+        ] {
+            assert!(parse(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_resume() {
+        let program = parse(
+            r#"
+            walk player 10 10
+            say player "Done."
+            if met
+                room hall
+            end
+            wait 30
+            set met
+            "#,
+        )
+        .unwrap();
+        let mut host = TestHost::default();
+        let mut script = Script::new(program);
+        let dt = Duration::from_millis(10);
+
+        assert!(script.resume(&mut host, dt));
+        assert!(script.resume(&mut host, dt));
+        assert_eq!(host.walking["player"], point(10.0, 10.0));
+        host.walking.clear();
+
+        assert!(script.resume(&mut host, dt));
+        assert_eq!(host.said, vec!["Done."]);
+        host.talking = false;
+
+        // the branch is skipped and the wait takes three more ticks
+        assert!(script.resume(&mut host, dt));
+        assert!(script.resume(&mut host, dt));
+        assert!(script.resume(&mut host, dt));
+        assert!(!host.flags.contains("met"));
+        assert!(!script.resume(&mut host, dt));
+        assert!(host.flags.contains("met"));
+        assert_eq!(host.room, "");
+    }
+
+    #[test]
+    fn test_wait_for_animation() {
+        let program = parse(
+            r#"
+            anim player resources/fox_walk.png hold frames 1 2 speed 2
+            anim player resources/fox_walk.png
+            set done
+            "#,
+        )
+        .unwrap();
+        let control = Control {
+            playback: Playback::HoldLast,
+            range: Some((1, 2)),
+            speed: 2.0,
+        };
+        assert!(matches!(&program.ops[0], Op::Play(_, _, c) if *c == control));
+
+        let mut host = TestHost::default();
+        let mut script = Script::new(program);
+        let dt = Duration::from_millis(10);
+        assert!(script.resume(&mut host, dt));
+        assert!(script.resume(&mut host, dt));
+        host.finish_animation("player");
+        assert!(!script.resume(&mut host, dt));
+        assert!(host.flags.contains("done"));
+    }
+}
@@ -0,0 +1,491 @@
+// sprite sheets: one png holding the frames of several animations, described
+// either by a grid in a ron file or by the json Aseprite and TexturePacker
+// export next to the png. every animation cut out of a sheet shares its pixels.
+//
+// images refer to an animation in a sheet as `sheet.ron#walk`. a sheet used
+// without a name has to hold exactly one, and its file has to end in
+// `.sheet.ron` or `.sheet.json` so it isn't taken for an image.
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    error::Error,
+    geometry::{point, rect, Point, Rect},
+    image::{AnimatedImage, Bitmap, Control, Frame, Image, Playback},
+    scene, vfs,
+};
+
+const DEFAULT_DURATION: u64 = 100;
+
+// `sheet.ron#walk` into the sheet's path and the animation's name
+pub fn split(path: &Path) -> (PathBuf, Option<&str>) {
+    match path.to_str().and_then(|s| s.rsplit_once('#')) {
+        Some((file, name)) => (file.into(), Some(name)),
+        None => (path.to_owned(), None),
+    }
+}
+
+// anything else is loaded as an image, so a scene file in an image's place
+// fails as a bad image rather than a bad sheet
+pub fn is_sheet(path: &Path) -> bool {
+    let (file, name) = split(path);
+    let file = file.to_string_lossy();
+    name.is_some() || file.ends_with(".sheet.ron") || file.ends_with(".sheet.json")
+}
+
+pub fn load_animation(path: &Path) -> Result<AnimatedImage, Error> {
+    let (file, name) = split(path);
+    Sheet::load(file)?.animation(name)
+}
+
+// a sheet laid out as a grid of equally sized frames, numbered row by row
+#[derive(Debug, Deserialize)]
+struct GridData {
+    image: PathBuf,
+    frame: (usize, usize),
+    animations: HashMap<String, GridAnimation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GridAnimation {
+    frames: Vec<usize>,
+    // in milliseconds, one for every frame or one for all of them
+    #[serde(default)]
+    durations: Vec<u64>,
+    // the pixel in a frame that goes where the image is anchored, the middle
+    // of its bottom edge
+    #[serde(default)]
+    pivot: Option<(f64, f64)>,
+    #[serde(default)]
+    playback: Playback,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonData {
+    // an array, or an object keyed by file name in the order of the frames
+    frames: serde_json::Value,
+    meta: JsonMeta,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonMeta {
+    // relative to the json file
+    image: PathBuf,
+    #[serde(default)]
+    frame_tags: Vec<JsonTag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonFrame {
+    frame: JsonRect,
+    #[serde(default)]
+    rotated: bool,
+    // where a trimmed frame sits in the untrimmed one
+    sprite_source_size: Option<JsonRect>,
+    source_size: Option<JsonSize>,
+    duration: Option<u64>,
+    // relative to the untrimmed frame's size
+    pivot: Option<JsonPoint>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRect {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonSize {
+    w: usize,
+    h: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonPoint {
+    x: f64,
+    y: f64,
+}
+
+#[derive(Debug)]
+pub struct Sheet {
+    path: PathBuf,
+    // the png the frames are cut out of, once loaded from disk
+    image: Option<PathBuf>,
+    animations: HashMap<String, AnimatedImage>,
+}
+impl Sheet {
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Self, Error> {
+        let path = path.as_ref();
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => {
+                let s = vfs::read_to_string(path)?;
+                let data: JsonData = parse_json(path, &s)?;
+                let image = path
+                    .parent()
+                    .unwrap_or(Path::new(""))
+                    .join(&data.meta.image);
+                let mut sheet = Self::from_json(path, data, &bitmap(&image)?)?;
+                sheet.image = Some(image);
+                Ok(sheet)
+            }
+            _ => {
+                let data: GridData = scene::load(path)?;
+                let image = data.image.clone();
+                let mut sheet = Self::from_grid(path, data, &bitmap(&image)?)?;
+                sheet.image = Some(image);
+                Ok(sheet)
+            }
+        }
+    }
+    // whether it was made from the file
+    pub fn uses(&self, file: &Path) -> bool {
+        self.path == file || self.image.as_deref() == Some(file)
+    }
+    fn from_grid(path: &Path, data: GridData, sheet: &Bitmap) -> Result<Self, Error> {
+        let invalid = |reason: String| Error::InvalidScene(path.to_owned(), reason);
+        let (w, h) = data.frame;
+        let columns = if w == 0 || h == 0 {
+            0
+        } else {
+            sheet.cols() / w
+        };
+        if columns == 0 {
+            return Err(invalid(format!("{}x{} frames don't fit the sheet", w, h)));
+        }
+        let count = columns * (sheet.rows() / h);
+        let mut animations = HashMap::new();
+        for (name, a) in data.animations {
+            let durations = match a.durations.len() {
+                0 => vec![DEFAULT_DURATION; a.frames.len()],
+                1 => vec![a.durations[0]; a.frames.len()],
+                n if n == a.frames.len() => a.durations,
+                n => {
+                    return Err(invalid(format!(
+                        "'{}' has {} frames but {} durations",
+                        name,
+                        a.frames.len(),
+                        n
+                    )))
+                }
+            };
+            let size = rect(w, h);
+            let mut frames = vec![];
+            for (&i, ms) in a.frames.iter().zip(durations) {
+                if i >= count {
+                    return Err(invalid(format!("'{}' uses frame {} of {}", name, i, count)));
+                }
+                let region = sheet.region((i % columns) * w, (i / columns) * h, size);
+                let offset = pivot_offset(point(0.0, 0.0), size, a.pivot);
+                frames.push(Frame::from_bitmap(
+                    region,
+                    offset,
+                    Duration::from_millis(ms),
+                ));
+            }
+            if frames.is_empty() {
+                return Err(invalid(format!("'{}' has no frames", name)));
+            }
+            let mut animation = AnimatedImage::new(frames, size);
+            animation.play(Control {
+                playback: a.playback,
+                ..Control::default()
+            });
+            animations.insert(name, animation);
+        }
+        Ok(Self {
+            path: path.to_owned(),
+            image: None,
+            animations,
+        })
+    }
+    fn from_json(path: &Path, data: JsonData, sheet: &Bitmap) -> Result<Self, Error> {
+        let invalid = |reason: String| Error::InvalidScene(path.to_owned(), reason);
+        let frames: Vec<JsonFrame> = match data.frames {
+            serde_json::Value::Array(frames) => frames
+                .into_iter()
+                .map(|f| from_value(path, f))
+                .collect::<Result<_, _>>()?,
+            serde_json::Value::Object(frames) => frames
+                .into_iter()
+                .map(|(_, f)| from_value(path, f))
+                .collect::<Result<_, _>>()?,
+            _ => return Err(invalid("'frames' is neither an array nor an object".into())),
+        };
+        let mut cut = vec![];
+        for (i, f) in frames.iter().enumerate() {
+            let JsonRect { x, y, w, h } = f.frame;
+            if f.rotated {
+                return Err(invalid(format!("frame {} is rotated", i)));
+            }
+            if x + w > sheet.cols() || y + h > sheet.rows() {
+                return Err(invalid(format!("frame {} is outside the sheet", i)));
+            }
+            let trim = f.sprite_source_size.as_ref().map_or((0, 0), |r| (r.x, r.y));
+            let size = f
+                .source_size
+                .as_ref()
+                .map_or(rect(w, h), |s| rect(s.w, s.h));
+            let pivot = f
+                .pivot
+                .as_ref()
+                .map(|p| (p.x * size.w as f64, p.y * size.h as f64));
+            let offset = pivot_offset(point(trim.0 as f64, trim.1 as f64), size, pivot);
+            let ms = f.duration.unwrap_or(DEFAULT_DURATION);
+            let frame = Frame::from_bitmap(
+                sheet.region(x, y, rect(w, h)),
+                offset,
+                Duration::from_millis(ms),
+            );
+            cut.push((frame, size));
+        }
+        if cut.is_empty() {
+            return Err(invalid("the sheet has no frames".into()));
+        }
+
+        // without tags every frame makes up a single animation
+        let tags = match data.meta.frame_tags {
+            tags if tags.is_empty() => vec![JsonTag {
+                name: "default".into(),
+                from: 0,
+                to: cut.len() - 1,
+                direction: String::new(),
+            }],
+            tags => tags,
+        };
+        let mut animations = HashMap::new();
+        for tag in tags {
+            if tag.from > tag.to || tag.to >= cut.len() {
+                return Err(invalid(format!(
+                    "'{}' uses frames {} to {} of {}",
+                    tag.name,
+                    tag.from,
+                    tag.to,
+                    cut.len()
+                )));
+            }
+            let forward = tag.from..=tag.to;
+            let (order, playback): (Vec<usize>, _) = match tag.direction.as_str() {
+                "" | "forward" => (forward.collect(), Playback::Loop),
+                "reverse" => (forward.rev().collect(), Playback::Loop),
+                "pingpong" => (forward.collect(), Playback::PingPong),
+                d => {
+                    return Err(invalid(format!(
+                        "'{}' has unknown direction '{}'",
+                        tag.name, d
+                    )))
+                }
+            };
+            let size = cut[tag.from].1;
+            let frames = order.into_iter().map(|i| cut[i].0.clone()).collect();
+            let mut animation = AnimatedImage::new(frames, size);
+            animation.play(Control {
+                playback,
+                ..Control::default()
+            });
+            animations.insert(tag.name, animation);
+        }
+        Ok(Self {
+            path: path.to_owned(),
+            image: None,
+            animations,
+        })
+    }
+    // whether any animation handed out is still around
+    pub fn in_use(&self) -> bool {
+        self.animations.values().any(|a| a.handles() > 1)
+    }
+    // frames are shared with the sheet, only the playback state is new
+    pub fn animation(&self, name: Option<&str>) -> Result<AnimatedImage, Error> {
+        let found = match name {
+            Some(name) => self.animations.get(name),
+            None if self.animations.len() == 1 => self.animations.values().next(),
+            None => {
+                let mut names: Vec<_> = self.animations.keys().map(String::as_str).collect();
+                names.sort_unstable();
+                return Err(Error::InvalidScene(
+                    self.path.clone(),
+                    format!("pick one of the animations: {}", names.join(", ")),
+                ));
+            }
+        };
+        found.cloned().ok_or_else(|| {
+            Error::InvalidScene(
+                self.path.clone(),
+                format!("no animation named '{}'", name.unwrap_or_default()),
+            )
+        })
+    }
+}
+
+// moves a frame so its pivot lands on the middle of the image's bottom edge
+fn pivot_offset(trim: Point, size: Rect, pivot: Option<(f64, f64)>) -> Point {
+    match pivot {
+        Some((x, y)) => point(trim.x + size.w as f64 / 2.0 - x, trim.y + size.h as f64 - y),
+        None => trim,
+    }
+}
+
+fn bitmap(path: &Path) -> Result<Bitmap, Error> {
+    match Image::load(scene::asset(path)?)? {
+        Image::Static(i) => Ok(i.data().clone()),
+        Image::Animated(_) => Err(Error::InvalidScene(
+            path.to_owned(),
+            "a sprite sheet has to be a still png".into(),
+        )),
+    }
+}
+
+fn parse_json<T: DeserializeOwned>(path: &Path, s: &str) -> Result<T, Error> {
+    serde_json::from_str(s).map_err(|e| Error::InvalidScene(path.to_owned(), e.to_string()))
+}
+
+fn from_value<T: DeserializeOwned>(path: &Path, v: serde_json::Value) -> Result<T, Error> {
+    serde_json::from_value(v).map_err(|e| Error::InvalidScene(path.to_owned(), e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::Duration};
+
+    use crate::{
+        error::Error,
+        geometry::{point, rect, Point},
+        image::{AnimatedImage, Bitmap, Image},
+        scene, Buffer,
+    };
+
+    use super::{bitmap, is_sheet, parse_json, split, Sheet};
+
+    fn fox() -> Bitmap {
+        bitmap(Path::new("resources/fox_sheet.png")).unwrap()
+    }
+
+    fn render(image: &Image, p: Point) -> Buffer {
+        let mut buffer = Buffer::new(rect(120, 120));
+        image.draw(&mut buffer, p);
+        buffer
+    }
+
+    #[test]
+    fn test_split() {
+        let (file, name) = split(Path::new("resources/fox_sheet.json#walk"));
+        assert_eq!(file, Path::new("resources/fox_sheet.json"));
+        assert_eq!(name, Some("walk"));
+        assert_eq!(split(Path::new("resources/fox.png")).1, None);
+
+        assert!(is_sheet(Path::new("resources/fox_sheet.json#walk")));
+        assert!(is_sheet(Path::new("resources/walk.sheet.ron")));
+        assert!(!is_sheet(Path::new("resources/fox_sheet.json")));
+        let res = Image::load("resources/rooms/start.ron");
+        assert!(matches!(res, Err(Error::Decode(_, _))));
+    }
+
+    #[test]
+    fn test_load_json() {
+        let sheet = Sheet::load("resources/fox_sheet.json").unwrap();
+        assert!(sheet.animation(Some("run")).is_err());
+        assert!(sheet.animation(None).is_err());
+        let talk = Image::load("resources/fox_sheet.json#talk").unwrap();
+        assert_eq!(talk.size(), rect(52, 48));
+
+        // the walk frames are the ones of the apng they were exported from
+        let mut apng = Image::load("resources/fox_walk.png").unwrap();
+        let mut walk = Image::Animated(sheet.animation(Some("walk")).unwrap());
+        let p = point(20.0, 20.0);
+        for _ in 0..4 {
+            assert_eq!(render(&apng, p).data(), render(&walk, p).data());
+            for image in [&mut apng, &mut walk] {
+                image
+                    .animation_mut()
+                    .unwrap()
+                    .update(Duration::from_millis(120));
+            }
+        }
+    }
+
+    fn grid(s: &str) -> Result<Sheet, Error> {
+        let path = Path::new("test.ron");
+        Sheet::from_grid(path, scene::parse(path, s).unwrap(), &fox())
+    }
+
+    #[test]
+    fn test_grid() {
+        let talk = |pivot: &str| -> AnimatedImage {
+            let s = format!(
+                r#"(
+                    image: "resources/fox_sheet.png",
+                    frame: (52, 48),
+                    animations: {{"talk": (frames: [4, 5], durations: [150], pivot: {})}},
+                )"#,
+                pivot
+            );
+            grid(&s).unwrap().animation(None).unwrap()
+        };
+        let mut plain = talk("None");
+        plain.update(Duration::from_millis(150));
+        assert_eq!(plain.position(), (1, Duration::ZERO));
+
+        // the pivot moves the frame so it lands on the bottom middle
+        let plain = Image::Animated(talk("None"));
+        let pivoted = Image::Animated(talk("Some((20.0, 40.0))"));
+        assert_eq!(
+            render(&plain, point(26.0, 28.0)).data(),
+            render(&pivoted, point(20.0, 20.0)).data()
+        );
+
+        for s in [
+            r#"(image: "", frame: (52, 48), animations: {"a": (frames: [8])})"#,
+            r#"(image: "", frame: (52, 48), animations: {"a": (frames: [])})"#,
+            r#"(image: "", frame: (52, 48), animations: {"a": (frames: [0, 1, 2], durations: [1, 2])})"#,
+            r#"(image: "", frame: (0, 48), animations: {})"#,
+        ] {
+            assert!(matches!(grid(s), Err(Error::InvalidScene(_, _))), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_json_trimmed() {
+        let path = Path::new("test.json");
+        let s = r#"{
+            "frames": {
+                "b": {"frame": {"x": 0, "y": 0, "w": 30, "h": 40}, "spriteSourceSize": {"x": 10, "y": 8, "w": 30, "h": 40}, "sourceSize": {"w": 52, "h": 48}},
+                "a": {"frame": {"x": 0, "y": 0, "w": 52, "h": 48}}
+            },
+            "meta": {"image": "fox_sheet.png", "frameTags": [{"name": "bounce", "from": 0, "to": 1, "direction": "pingpong"}]}
+        }"#;
+        let sheet = Sheet::from_json(path, parse_json(path, s).unwrap(), &fox()).unwrap();
+        // frames keep the order of the file rather than of their names, and
+        // trimmed ones are put back where they were cut from
+        let mut bounce = Image::Animated(sheet.animation(Some("bounce")).unwrap());
+        let mut trimmed = Buffer::new(rect(120, 120));
+        trimmed.draw_bmp(&fox().region(0, 0, rect(30, 40)), point(30.0, 28.0));
+        assert_eq!(render(&bounce, point(20.0, 20.0)).data(), trimmed.data());
+        bounce
+            .animation_mut()
+            .unwrap()
+            .update(Duration::from_millis(100));
+        assert_eq!(bounce.animation().unwrap().position(), (1, Duration::ZERO));
+
+        let s = r#"{"frames": [{"frame": {"x": 200, "y": 0, "w": 52, "h": 48}}], "meta": {"image": "fox_sheet.png"}}"#;
+        let res = Sheet::from_json(path, parse_json(path, s).unwrap(), &fox());
+        assert!(matches!(res, Err(Error::InvalidScene(_, _))));
+    }
+}
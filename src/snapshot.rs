@@ -0,0 +1,228 @@
+// golden-image testing: drive the game through scripted input without a
+// window and compare the final frame against a reference png
+//
+// run with `UPDATE_SNAPSHOTS=1` to (re)write the references
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use winit::event::{ElementState, VirtualKeyCode};
+
+use crate::{
+    backend::{Backend, MemoryBackend},
+    buffer::Buffer,
+    game_state::{GameState, SCREEN, TICK},
+    geometry::Point,
+    image::{Bitmap, Image},
+};
+
+const SNAPSHOT_DIR: &str = "tests/snapshots";
+
+pub enum Step {
+    MouseOver(Point),
+    Click,
+    Key(VirtualKeyCode),
+    Ticks(usize),
+}
+
+pub fn render(steps: &[Step]) -> Buffer {
+    let mut game_state = GameState::new("resources/game.ron").unwrap();
+    // the same frames in debug and release builds
+    game_state.debug_overlay = false;
+    for step in steps {
+        match step {
+            Step::MouseOver(p) => game_state.mouse_over(*p),
+            Step::Click => game_state.mouse_click(ElementState::Pressed),
+            Step::Key(k) => game_state.key_pressed(*k),
+            Step::Ticks(n) => (0..*n).for_each(|_| game_state.update(TICK)),
+        }
+    }
+    let mut buffer = Buffer::new(SCREEN);
+    game_state.draw(&mut buffer);
+    buffer
+}
+
+// panics with the number of mismatched pixels, leaving the actual frame and a
+// diff image next to the reference
+pub fn assert_snapshot(name: &str, buffer: &Buffer, tolerance: u8) {
+    let dir = Path::new(SNAPSHOT_DIR);
+    let reference = dir.join(format!("{}.png", name));
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(dir).unwrap();
+        save(buffer, &reference);
+        return;
+    }
+    assert!(
+        reference.exists(),
+        "{}: no reference, run with UPDATE_SNAPSHOTS=1 to write it",
+        reference.display()
+    );
+
+    let expected = match Image::load(&reference).unwrap() {
+        Image::Static(i) => i,
+        Image::Animated(_) => panic!("{}: reference is animated", reference.display()),
+    };
+    let expected = expected.data();
+    let size = buffer.size();
+    assert_eq!(
+        (expected.cols(), expected.rows()),
+        size.wh(),
+        "{}: frame size differs",
+        reference.display()
+    );
+
+    let (mismatched, diff) = compare(buffer.data(), expected.data(), tolerance);
+    if mismatched > 0 {
+        let out = output_dir();
+        let actual_path = out.join(format!("{}.actual.png", name));
+        let diff_path = out.join(format!("{}.diff.png", name));
+        save(buffer, &actual_path);
+        let mut diff_buffer = Buffer::new(size);
+        diff_buffer.draw_opaque(&Bitmap::new(diff, size));
+        save(&diff_buffer, &diff_path);
+        panic!(
+            "{}: {} pixels differ by more than {}, see {}",
+            name,
+            mismatched,
+            tolerance,
+            diff_path.display()
+        );
+    }
+}
+
+// returns the number of pixels with any channel off by more than `tolerance`,
+// and an image with those pixels in red over a faded copy of the expected frame
+pub fn compare(actual: &[u8], expected: &[u8], tolerance: u8) -> (usize, Vec<u8>) {
+    let mut mismatched = 0;
+    let diff = actual
+        .chunks_exact(4)
+        .zip(expected.chunks_exact(4))
+        .flat_map(|(a, e)| {
+            if a.iter().zip(e).any(|(a, e)| a.abs_diff(*e) > tolerance) {
+                mismatched += 1;
+                [255, 0, 0, 255]
+            } else {
+                [e[0] / 3, e[1] / 3, e[2] / 3, 255]
+            }
+        })
+        .collect();
+    (mismatched, diff)
+}
+
+fn output_dir() -> PathBuf {
+    let dir = Path::new("target").join("snapshots");
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn save(buffer: &Buffer, path: &Path) {
+    let mut backend = MemoryBackend::new();
+    backend.present(buffer).unwrap();
+    backend.save_png(path).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::event::VirtualKeyCode;
+
+    use crate::geometry::point;
+
+    use super::{assert_snapshot, compare, render, Step};
+
+    #[test]
+    fn test_compare() {
+        let expected = [10, 10, 10, 255, 200, 200, 200, 255];
+        let actual = [12, 10, 10, 255, 100, 200, 200, 255];
+        let (mismatched, diff) = compare(&actual, &expected, 2);
+        assert_eq!(mismatched, 1);
+        assert_eq!(&diff[4..8], &[255, 0, 0, 255]);
+        let (mismatched, _) = compare(&actual, &expected, 100);
+        assert_eq!(mismatched, 0);
+    }
+
+    #[test]
+    fn snapshot_start_room() {
+        let frame = render(&[Step::MouseOver(point(320.0, 200.0)), Step::Ticks(1)]);
+        assert_snapshot("start_room", &frame, 2);
+    }
+
+    #[test]
+    fn snapshot_walk() {
+        let frame = render(&[
+            Step::MouseOver(point(300.0, 400.0)),
+            Step::Ticks(1),
+            Step::Click,
+            Step::Ticks(60),
+        ]);
+        assert_snapshot("walk", &frame, 2);
+    }
+
+    #[test]
+    fn snapshot_look_at() {
+        let frame = render(&[
+            Step::Key(VirtualKeyCode::Key2),
+            Step::MouseOver(point(470.0, 340.0)),
+            Step::Ticks(1),
+            Step::Click,
+            Step::Ticks(400),
+        ]);
+        assert_snapshot("look_at", &frame, 2);
+    }
+
+    #[test]
+    fn snapshot_pick_up() {
+        let frame = render(&[
+            Step::Key(VirtualKeyCode::Key3),
+            Step::MouseOver(point(470.0, 340.0)),
+            Step::Ticks(1),
+            Step::Click,
+            Step::Ticks(400),
+            Step::MouseOver(point(370.0, 445.0)),
+            Step::Ticks(1),
+        ]);
+        assert_snapshot("pick_up", &frame, 2);
+    }
+
+    #[test]
+    fn snapshot_room_transition() {
+        let frame = render(&[
+            Step::MouseOver(point(70.0, 420.0)),
+            Step::Ticks(1),
+            Step::Click,
+            Step::Ticks(400),
+            Step::MouseOver(point(300.0, 300.0)),
+            Step::Ticks(1),
+        ]);
+        assert_snapshot("room_transition", &frame, 2);
+    }
+
+    #[test]
+    fn snapshot_dialogue() {
+        let frame = render(&[
+            Step::MouseOver(point(70.0, 420.0)),
+            Step::Ticks(1),
+            Step::Click,
+            Step::Ticks(400),
+            Step::Key(VirtualKeyCode::Key5),
+            Step::MouseOver(point(250.0, 310.0)),
+            Step::Ticks(1),
+            Step::Click,
+            Step::Ticks(150),
+            Step::MouseOver(point(30.0, 455.0)),
+            Step::Ticks(1),
+        ]);
+        assert_snapshot("dialogue", &frame, 2);
+    }
+
+    #[test]
+    fn snapshot_walk_west() {
+        let frame = render(&[
+            Step::MouseOver(point(70.0, 160.0)),
+            Step::Ticks(1),
+            Step::Click,
+            Step::Ticks(20),
+        ]);
+        assert_snapshot("walk_west", &frame, 2);
+    }
+}
@@ -1,6 +1,15 @@
-use crate::{geometry::Rect, image::Bitmap};
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
+use crate::{error::Error, geometry::Rect, image::Bitmap, vfs};
+
+pub const FONT_PATH: &str = "resources/font.bdf";
 const FONT_INNER: [u8; 4] = [255, 255, 255, 255];
+// drawn for characters the font doesn't have
+const REPLACEMENT: char = '?';
 
 pub struct TextObject {
     pub buffer: Vec<u8>,
@@ -40,31 +49,43 @@ impl TextObject {
     }
 }
 
+pub fn load_font(path: &Path) -> Result<bdf::Font, Error> {
+    bdf::read(Cursor::new(vfs::read(path)?)).map_err(|e| match e {
+        bdf::Error::IO(e) => Error::Io(path.to_owned(), e),
+        e => Error::Decode(path.to_owned(), e.to_string()),
+    })
+}
+
 #[derive(Debug)]
 pub struct GlyphWriter {
-    font: bdf::Font,
+    path: PathBuf,
+    font: Rc<bdf::Font>,
     width: usize,
     height: usize,
 }
 impl GlyphWriter {
-    pub fn new() -> Self {
-        let font = bdf::open("resources/font.bdf").unwrap();
+    pub fn new(path: &Path, font: Rc<bdf::Font>) -> Self {
         let height = font.bounds().height as usize;
         let width = font.bounds().width as usize;
         Self {
+            path: path.to_owned(),
             font,
             width,
             height,
         }
     }
-    fn make_codepoint(&self, codepoint: char) -> Vec<u8> {
+    fn make_codepoint(&self, codepoint: char) -> Result<Vec<u8>, Error> {
         let mut buf: Vec<u8> = vec![0; self.width * self.height * 4];
         for i in (3..buf.len()).step_by(4) {
             buf[i] = 128;
         }
 
         let fb = self.font.bounds();
-        let glyph = self.font.glyphs().get(&codepoint).unwrap();
+        let glyph = self
+            .font
+            .glyphs()
+            .get(&codepoint)
+            .ok_or_else(|| Error::MissingGlyph(self.path.clone(), codepoint))?;
         let gb = glyph.bounds();
 
         let y_off = (self.height - gb.height as usize) as i32 - gb.y + fb.y;
@@ -77,16 +98,48 @@ impl GlyphWriter {
                 }
             }
         }
-        buf
+        Ok(buf)
     }
 
+    // characters missing from the font come out as `REPLACEMENT`, or blank
+    // if that's missing too
     pub fn make_string(&self, string: &str) -> TextObject {
         let mut output = TextObject::new(self.width, self.height);
         for c in string.chars() {
-            let buf = self.make_codepoint(c);
+            let buf = self
+                .make_codepoint(c)
+                .or_else(|_| self.make_codepoint(REPLACEMENT))
+                .unwrap_or_else(|_| vec![0; self.width * self.height * 4]);
             output.add_glyph(&buf);
         }
 
         output
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{assets::Assets, error::Error};
+
+    use super::{load_font, FONT_PATH, REPLACEMENT};
+
+    #[test]
+    fn test_missing_glyph() {
+        let writer = Assets::default().font(FONT_PATH).unwrap();
+        let missing = '\u{1F98A}';
+        assert!(matches!(
+            writer.make_codepoint(missing),
+            Err(Error::MissingGlyph(_, c)) if c == missing
+        ));
+        let text = writer.make_string(&format!("a{}", missing)).to_bmp();
+        let replaced = writer.make_string(&format!("a{}", REPLACEMENT)).to_bmp();
+        assert_eq!(text, replaced);
+
+        let font = load_font(Path::new("resources/missing.bdf"));
+        assert!(matches!(font, Err(Error::Io(_, _))));
+        let font = load_font(Path::new("resources/game.ron"));
+        assert!(matches!(font, Err(Error::Decode(_, _))));
+    }
+}
@@ -0,0 +1,82 @@
+// where the loaders get their files: the mounted archive if it has the file,
+// the loose file on disk otherwise, so a shipped game and a checkout of the
+// resources directory look the same to the rest of the engine
+use std::{cell::RefCell, fs, io, path::Path, rc::Rc};
+
+use crate::{archive::Archive, error::Error};
+
+thread_local! {
+    static ARCHIVE: RefCell<Option<Rc<Archive>>> = const { RefCell::new(None) };
+}
+
+pub fn mount(archive: Archive) {
+    ARCHIVE.with(|a| *a.borrow_mut() = Some(Rc::new(archive)));
+}
+
+fn archive() -> Option<Rc<Archive>> {
+    ARCHIVE.with(|a| a.borrow().clone())
+}
+
+pub fn read<T: AsRef<Path>>(path: T) -> Result<Vec<u8>, Error> {
+    let path = path.as_ref();
+    if let Some(data) = archive().and_then(|a| a.read(path)) {
+        return data;
+    }
+    fs::read(path).map_err(|e| Error::Io(path.to_owned(), e))
+}
+
+pub fn read_to_string<T: AsRef<Path>>(path: T) -> Result<String, Error> {
+    let path = path.as_ref();
+    String::from_utf8(read(path)?).map_err(|e| {
+        Error::Io(
+            path.to_owned(),
+            io::Error::new(io::ErrorKind::InvalidData, e),
+        )
+    })
+}
+
+// fails the way reading it would if the file is in neither place
+pub fn check<T: AsRef<Path>>(path: T) -> Result<(), Error> {
+    let path = path.as_ref();
+    if archive().is_some_and(|a| a.contains(path)) {
+        return Ok(());
+    }
+    fs::metadata(path).map_err(|e| Error::Io(path.to_owned(), e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::{
+        archive::{self, Archive},
+        error::Error,
+    };
+
+    use super::{check, mount, read, read_to_string};
+
+    #[test]
+    fn test_mount() {
+        let root = env::temp_dir().join("crust_engine_test_vfs");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("rooms")).unwrap();
+        let room = root.join("rooms/start.ron");
+        fs::write(&room, "()").unwrap();
+        let pak = env::temp_dir().join("crust_engine_test_vfs.pak");
+        fs::write(&pak, archive::pack(&root).unwrap()).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        assert!(matches!(check(&room), Err(Error::Io(_, _))));
+
+        mount(Archive::open(&pak).unwrap());
+        fs::remove_file(&pak).unwrap();
+        check(&room).unwrap();
+        assert_eq!(read_to_string(&room).unwrap(), "()");
+
+        // loose files are still found next to the archive
+        let fox = fs::read("resources/fox.png").unwrap();
+        assert_eq!(read("resources/fox.png").unwrap(), fox);
+        let missing = read(root.join("rooms/hall.ron"));
+        assert!(matches!(missing, Err(Error::Io(_, _))));
+    }
+}
@@ -0,0 +1,119 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// notices files under a directory changing by polling their modification
+// times, for reloading assets while the game runs
+#[derive(Debug)]
+pub struct Watcher {
+    root: PathBuf,
+    last_poll: Instant,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+impl Watcher {
+    pub fn new<T: AsRef<Path>>(root: T) -> Self {
+        let root = root.as_ref().to_owned();
+        let modified = scan(&root);
+        Self {
+            root,
+            last_poll: Instant::now(),
+            modified,
+        }
+    }
+    // files changed, added or removed since the last look, which happens at
+    // most every `POLL_INTERVAL`
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return vec![];
+        }
+        self.last_poll = Instant::now();
+        self.changes()
+    }
+    fn changes(&mut self) -> Vec<PathBuf> {
+        let modified = scan(&self.root);
+        let mut changed: Vec<_> = modified
+            .iter()
+            .filter(|(path, time)| self.modified.get(*path) != Some(time))
+            .map(|(path, _)| path.clone())
+            .chain(
+                self.modified
+                    .keys()
+                    .filter(|path| !modified.contains_key(*path))
+                    .cloned(),
+            )
+            .collect();
+        changed.sort();
+        self.modified = modified;
+        changed
+    }
+}
+
+// every file in the tree, unreadable ones are skipped
+fn scan(root: &Path) -> HashMap<PathBuf, SystemTime> {
+    let mut files = HashMap::new();
+    let mut dirs = vec![root.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.metadata() {
+                Ok(m) if m.is_dir() => dirs.push(path),
+                Ok(m) => {
+                    if let Ok(time) = m.modified() {
+                        files.insert(path, time);
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs::{self, File},
+        time::{Duration, SystemTime},
+    };
+
+    use super::Watcher;
+
+    #[test]
+    fn test_changes() {
+        let root = env::temp_dir().join("crust_engine_test_watch");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("rooms")).unwrap();
+        let room = root.join("rooms/start.ron");
+        let image = root.join("fox.png");
+        fs::write(&room, "()").unwrap();
+        fs::write(&image, "").unwrap();
+
+        let mut watcher = Watcher::new(&root);
+        assert!(watcher.changes().is_empty());
+
+        let later = SystemTime::now() + Duration::from_secs(10);
+        File::options()
+            .write(true)
+            .open(&room)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert_eq!(watcher.changes(), vec![room.clone()]);
+        assert!(watcher.changes().is_empty());
+
+        fs::remove_file(&image).unwrap();
+        let added = root.join("rooms/hall.ron");
+        fs::write(&added, "()").unwrap();
+        assert_eq!(watcher.changes(), vec![image, added]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}